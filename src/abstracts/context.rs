@@ -0,0 +1,23 @@
+/// Kernel Thread Context Hardware Abstraction Layer
+pub trait ContextHAL {
+    /// Registers of a suspended kernel thread.
+    type Context: Default + Send;
+
+    /// Build a context which calls `entry(arg)` on the stack ending at
+    /// `stack_top` once switched to.
+    ///
+    /// # Safety
+    ///
+    /// `stack_top` must be the end of a writable stack owned by the new
+    /// thread, large enough for `entry`.
+    unsafe fn new_context(stack_top: usize, entry: extern "C" fn(usize) -> !, arg: usize) -> Self::Context;
+
+    /// Save the registers of the running thread into `prev` and resume the
+    /// thread saved in `next`. Returns once `prev` is switched to again.
+    ///
+    /// # Safety
+    ///
+    /// Must be called with interrupts disabled. `next` must hold a context
+    /// built by `new_context` or saved by `switch`, which no other CPU runs.
+    unsafe fn switch(prev: *mut Self::Context, next: *const Self::Context);
+}
\ No newline at end of file
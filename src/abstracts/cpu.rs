@@ -1,12 +1,26 @@
-hal_fn_def! {
-    pub mod cpu {
-        /// Current CPU ID.
-        pub fn cpu_id() -> u8;
+/// Processor Hardware Abstraction Layer
+pub trait CpuHAL {
+    /// Logical index of the current CPU, read from its per-CPU data.
+    fn cpu_id() -> usize;
 
-        /// Current CPU frequency in MHz.
-        pub fn cpu_frequency() -> u16;
+    /// Current CPU frequency in MHz.
+    fn cpu_frequency() -> u16;
 
-        /// Shutdown/reboot the machine.
-        pub fn reset() -> !;
-    }
+    /// Shutdown/reboot the machine.
+    fn reset() -> !;
+
+    /// Address of the per-CPU data of the current CPU.
+    fn local_base() -> usize;
+
+    /// Word at `offset` in the per-CPU data of the current CPU, read in a
+    /// single instruction so the caller can not migrate in between.
+    fn local_read(offset: usize) -> usize;
+
+    /// Make `base` the per-CPU data of the current CPU.
+    ///
+    /// # Safety
+    ///
+    /// `base` must point to a [`PerCpu`](crate::sys::smp::percpu::PerCpu)
+    /// block living forever, which no other CPU uses.
+    unsafe fn set_local_base(base: usize);
 }
\ No newline at end of file
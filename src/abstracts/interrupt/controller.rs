@@ -1,23 +1,51 @@
-use crate::common::structs::interrupt::manager::IrqResult;
+use crate::common::structs::interrupt::manager::{IrqError, IrqResult};
+use crate::common::structs::interrupt::stats::IrqInventory;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
-use core::cell::Cell;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use log::error;
+use spin::Once;
+
+/// Maximum number of interrupt controllers in the registry.
+pub const MAX_INTERRUPT_CONTROLLERS: usize = 8;
 
 pub type IrqHandler = Box<dyn Fn(usize) + Send + Sync>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IrqTriggerMode {
     Edge,
     Level,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IrqPolarity {
     ActiveHigh,
     ActiveLow,
 }
 
+/// CPUs allowed to service an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqAffinity {
+    /// Delivered to a single CPU, identified by its logical id.
+    Cpu(usize),
+    /// Delivered to the online CPU running at the lowest priority.
+    LowestPriority,
+}
+
+/// Message written by a device to raise a message signalled interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiMessage {
+    pub address: u64,
+    pub data: u32,
+}
+
 pub trait InterruptController {
+    /// Enable interrupts and wait for the next one. Called with interrupts
+    /// disabled, an interrupt becoming pending in between still ends the wait.
     fn wait_for_interrupt(&self) {
         core::hint::spin_loop();
     }
@@ -30,11 +58,228 @@ pub trait InterruptController {
     fn is_interrupt_enabled(&self) -> bool;
     fn mask_irq(&self, vector: usize) -> IrqResult;
     fn unmask_irq(&self, vector: usize) -> IrqResult;
-    fn register_irq_handler(&self, vector: usize, handler: IrqHandler) -> IrqResult;
+    fn register_irq_handler(&self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult;
     fn unregister_irq_handler(&self, vector: usize) -> IrqResult;
     fn handle_irq(&self, vector: usize) -> IrqResult;
+    fn set_affinity(&self, vector: usize, affinity: IrqAffinity) -> IrqResult {
+        Err(IrqError::NotSupported)
+    }
+    fn affinity(&self, vector: usize) -> IrqResult<IrqAffinity> {
+        Err(IrqError::NotSupported)
+    }
+    fn supports_msi(&self) -> bool {
+        false
+    }
+    /// Allocate a vector delivered to `cpu` for a message signalled interrupt,
+    /// returns the message the device must write.
+    fn register_msi_handler(&self, cpu: usize, name: &'static str, handler: IrqHandler) -> IrqResult<MsiMessage> {
+        Err(IrqError::NotSupported)
+    }
+    fn unregister_msi_handler(&self, msg: MsiMessage) -> IrqResult {
+        Err(IrqError::NotSupported)
+    }
+    /// Snapshot of the registered interrupts and their per-CPU counters.
+    fn inventory(&self) -> IrqInventory;
 }
 
+/// An interrupt controller together with the CPU vectors it owns.
+pub struct IrqDomain {
+    pub name: &'static str,
+    pub vectors: Range<usize>,
+    pub controller: Arc<dyn InterruptController + Send + Sync>,
+}
+
+/// Registry of the interrupt controllers of the system.
+///
+/// A CPU vector is routed to the controller owning it, and any other IRQ
+/// number to the first controller accepting it in [`InterruptController::is_valid_irq`].
+/// The first registered controller is the primary one, which also controls
+/// the interrupt flag of the CPU.
+///
+/// [`disable_interrupt`](InterruptController::disable_interrupt) and
+/// [`enable_interrupt`](InterruptController::enable_interrupt) nest: interrupts
+/// are only restored by the outermost `enable_interrupt`, and only if they were
+/// enabled before the outermost `disable_interrupt`. An `enable_interrupt`
+/// outside any critical section simply enables interrupts.
+///
+/// Controllers can only be added, so lookups never take a lock and are safe in
+/// interrupt context on any CPU.
 pub struct GlobalInterruptController {
-    controller: Cell<Option<Arc<dyn InterruptController + Send + Sync>>>,
+    domains: [Once<IrqDomain>; MAX_INTERRUPT_CONTROLLERS],
+    register_lock: IrqSpinLock<()>,
+    /// Depth of nested `disable_interrupt` on each CPU.
+    disable_depth: [AtomicUsize; KERNEL_MAX_CPU_NUM],
+    /// Whether interrupts were enabled before the outermost `disable_interrupt`.
+    restore_enabled: [AtomicBool; KERNEL_MAX_CPU_NUM],
+}
+
+impl GlobalInterruptController {
+    pub const fn new() -> Self {
+        Self {
+            domains: [const { Once::new() }; MAX_INTERRUPT_CONTROLLERS],
+            register_lock: IrqSpinLock::new(()),
+            disable_depth: [const { AtomicUsize::new(0) }; KERNEL_MAX_CPU_NUM],
+            restore_enabled: [const { AtomicBool::new(false) }; KERNEL_MAX_CPU_NUM],
+        }
+    }
+
+    /// Enable interrupts regardless of the nesting depth.
+    ///
+    /// Only for code which knows the exact interrupt state, like softirq
+    /// processing on interrupt exit.
+    pub fn enable_interrupt_raw(&self) -> IrqResult {
+        let controller = &self.primary()?.controller;
+        #[cfg(feature = "lockdep")]
+        sys::sync::lockdep::irqs_enabled();
+        controller.enable_interrupt()
+    }
+
+    /// Disable interrupts regardless of the nesting depth.
+    pub fn disable_interrupt_raw(&self) -> IrqResult {
+        self.primary()?.controller.disable_interrupt()
+    }
+
+    /// Add `controller` owning the CPU vectors `vectors`.
+    pub fn register(
+        &self,
+        name: &'static str,
+        vectors: Range<usize>,
+        controller: Arc<dyn InterruptController + Send + Sync>,
+    ) -> IrqResult {
+        let _guard = self.register_lock.lock();
+        if let Some(domain) = self.domains().find(|d| d.vectors.start < vectors.end && vectors.start < d.vectors.end) {
+            error!("Vectors {:?} of {} overlap with {}", vectors, name, domain.name);
+            return Err(IrqError::VectorConflict);
+        }
+        let slot = self.domains.iter()
+            .find(|slot| slot.get().is_none())
+            .ok_or(IrqError::TooManyControllers)?;
+        slot.call_once(|| IrqDomain { name, vectors, controller });
+        Ok(())
+    }
+
+    pub fn domains(&self) -> impl Iterator<Item=&IrqDomain> {
+        self.domains.iter().map_while(|slot| slot.get())
+    }
+
+    fn primary(&self) -> IrqResult<&IrqDomain> {
+        self.domains().next().ok_or(IrqError::NoController)
+    }
+
+    /// Controller owning the CPU vector `vector`.
+    fn by_vector(&self, vector: usize) -> IrqResult<&IrqDomain> {
+        self.domains()
+            .find(|d| d.vectors.contains(&vector))
+            .ok_or(IrqError::InvalidIrqVector)
+    }
+
+    /// The first controller able to deliver message signalled interrupts.
+    fn msi_domain(&self) -> IrqResult<&IrqDomain> {
+        self.domains()
+            .find(|d| d.controller.supports_msi())
+            .ok_or(IrqError::NotSupported)
+    }
+
+    /// Controller accepting the IRQ number `irq`.
+    fn by_irq(&self, irq: usize) -> IrqResult<&IrqDomain> {
+        self.domains()
+            .find(|d| d.controller.is_valid_irq(irq))
+            .ok_or(IrqError::InvalidIrqVector)
+    }
+}
+
+impl InterruptController for GlobalInterruptController {
+    fn wait_for_interrupt(&self) {
+        match self.primary() {
+            Ok(domain) => {
+                #[cfg(feature = "lockdep")]
+                sys::sync::lockdep::irqs_enabled();
+                domain.controller.wait_for_interrupt()
+            }
+            Err(_) => core::hint::spin_loop(),
+        }
+    }
+    fn is_valid_irq(&self, vector: usize) -> bool {
+        self.by_irq(vector).is_ok()
+    }
+    fn configure(&self, vector: usize, tm: IrqTriggerMode, pol: IrqPolarity) -> IrqResult {
+        self.by_irq(vector)?.controller.configure(vector, tm, pol)
+    }
+    fn enable_interrupt(&self) -> IrqResult {
+        let controller = &self.primary()?.controller;
+        let cpu = sys::smp::current_id();
+        match self.disable_depth[cpu].load(Ordering::Relaxed) {
+            0 => {
+                #[cfg(feature = "lockdep")]
+                sys::sync::lockdep::irqs_enabled();
+                controller.enable_interrupt()
+            }
+            1 => {
+                self.disable_depth[cpu].store(0, Ordering::Relaxed);
+                if self.restore_enabled[cpu].load(Ordering::Relaxed) {
+                    #[cfg(feature = "lockdep")]
+                    sys::sync::lockdep::irqs_enabled();
+                    controller.enable_interrupt()?;
+                }
+                Ok(())
+            }
+            depth => {
+                self.disable_depth[cpu].store(depth - 1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+    fn disable_interrupt(&self) -> IrqResult {
+        let controller = &self.primary()?.controller;
+        let enabled = controller.is_interrupt_enabled();
+        controller.disable_interrupt()?;
+        // Only the current CPU touches its own counters, with interrupts off
+        let cpu = sys::smp::current_id();
+        let depth = self.disable_depth[cpu].load(Ordering::Relaxed);
+        if depth == 0 {
+            self.restore_enabled[cpu].store(enabled, Ordering::Relaxed);
+        }
+        self.disable_depth[cpu].store(depth + 1, Ordering::Relaxed);
+        Ok(())
+    }
+    fn is_interrupt_enabled(&self) -> bool {
+        self.primary().map_or(false, |domain| domain.controller.is_interrupt_enabled())
+    }
+    fn mask_irq(&self, vector: usize) -> IrqResult {
+        self.by_irq(vector)?.controller.mask_irq(vector)
+    }
+    fn unmask_irq(&self, vector: usize) -> IrqResult {
+        self.by_irq(vector)?.controller.unmask_irq(vector)
+    }
+    fn register_irq_handler(&self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult {
+        self.by_irq(vector)?.controller.register_irq_handler(vector, name, handler)
+    }
+    fn unregister_irq_handler(&self, vector: usize) -> IrqResult {
+        self.by_irq(vector)?.controller.unregister_irq_handler(vector)
+    }
+    fn handle_irq(&self, vector: usize) -> IrqResult {
+        self.by_vector(vector)?.controller.handle_irq(vector)
+    }
+    fn set_affinity(&self, vector: usize, affinity: IrqAffinity) -> IrqResult {
+        self.by_irq(vector)?.controller.set_affinity(vector, affinity)
+    }
+    fn affinity(&self, vector: usize) -> IrqResult<IrqAffinity> {
+        self.by_irq(vector)?.controller.affinity(vector)
+    }
+    fn supports_msi(&self) -> bool {
+        self.msi_domain().is_ok()
+    }
+    fn register_msi_handler(&self, cpu: usize, name: &'static str, handler: IrqHandler) -> IrqResult<MsiMessage> {
+        self.msi_domain()?.controller.register_msi_handler(cpu, name, handler)
+    }
+    fn unregister_msi_handler(&self, msg: MsiMessage) -> IrqResult {
+        self.msi_domain()?.controller.unregister_msi_handler(msg)
+    }
+    fn inventory(&self) -> IrqInventory {
+        let mut inventory = IrqInventory::default();
+        for domain in self.domains() {
+            inventory.merge(domain.controller.inventory());
+        }
+        inventory
+    }
 }
\ No newline at end of file
@@ -0,0 +1,39 @@
+use crate::sys::smp::CpuMask;
+
+/// Destination of an inter-processor interrupt.
+#[derive(Debug, Clone, Copy)]
+pub enum IpiTarget {
+    /// A single CPU, identified by its logical id.
+    Cpu(usize),
+    /// Every CPU in the mask.
+    Mask(CpuMask),
+    /// Every CPU except the sender.
+    AllButSelf,
+    /// Every CPU including the sender.
+    All,
+}
+
+/// Delivery mode of an inter-processor interrupt.
+#[derive(Debug, Clone, Copy)]
+pub enum IpiKind {
+    /// Deliver the interrupt vector to the target.
+    Fixed(usize),
+    /// Non-maskable interrupt.
+    Nmi,
+    /// INIT request, puts the target into wait-for-SIPI state.
+    Init,
+    /// Startup request, the target begins execution at page `vector`.
+    Startup(u8),
+}
+
+/// Inter-Processor Interrupt Hardware Abstraction Layer
+pub trait IpiHAL {
+    /// Vector reserved for cross-CPU function calls.
+    const CALL_FUNCTION_VECTOR: usize;
+
+    /// Vector reserved for waking a CPU to pick up new work.
+    const RESCHEDULE_VECTOR: usize;
+
+    /// Send an inter-processor interrupt.
+    fn send_ipi(target: IpiTarget, kind: IpiKind);
+}
\ No newline at end of file
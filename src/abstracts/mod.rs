@@ -1,3 +1,6 @@
+pub mod context;
+pub mod cpu;
 pub mod memory;
 pub mod trap;
-pub mod interrupt;
\ No newline at end of file
+pub mod interrupt;
+pub mod time;
\ No newline at end of file
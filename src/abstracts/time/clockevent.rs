@@ -0,0 +1,72 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// Capabilities of a clock event device.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct ClockEventFeatures: u32 {
+        const PERIODIC = 1 << 0;
+        const ONESHOT  = 1 << 1;
+        /// Events are armed with an absolute deadline, e.g. TSC deadline.
+        const DEADLINE = 1 << 2;
+        /// Each CPU owns a private instance of the device.
+        const PER_CPU  = 1 << 3;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockEventMode {
+    Shutdown,
+    Periodic,
+    OneShot,
+    Deadline,
+}
+
+#[derive(Debug)]
+pub enum ClockEventError {
+    /// The requested mode is not supported by the device.
+    NotSupported,
+    /// The device has not been calibrated yet.
+    NotCalibrated,
+    /// The device is not in a mode accepting `program_event`.
+    InvalidMode,
+}
+
+pub type ClockEventResult<T = ()> = Result<T, ClockEventError>;
+
+/// A device which raises an interrupt at a programmed time.
+///
+/// For devices with [`ClockEventFeatures::PER_CPU`], every operation acts on
+/// the instance of the current CPU.
+pub trait ClockEventDevice: Send + Sync {
+    /// Name of the device.
+    fn name(&self) -> &'static str;
+
+    /// Capabilities of the device.
+    fn features(&self) -> ClockEventFeatures;
+
+    /// Smallest delta in nanoseconds accepted by `program_event`.
+    fn min_delta_ns(&self) -> u64;
+
+    /// Largest delta in nanoseconds accepted by `program_event`.
+    fn max_delta_ns(&self) -> u64;
+
+    /// Current operating mode.
+    fn mode(&self) -> ClockEventMode;
+
+    /// Stop the device.
+    fn shutdown(&self) -> ClockEventResult;
+
+    /// Raise an event every `period_ns` nanoseconds.
+    fn set_periodic(&self, period_ns: u64) -> ClockEventResult;
+
+    /// Switch to one-shot mode, events are armed by `program_event`.
+    fn set_oneshot(&self) -> ClockEventResult;
+
+    /// Switch to deadline mode, events are armed by `program_event`.
+    fn set_deadline(&self) -> ClockEventResult {
+        Err(ClockEventError::NotSupported)
+    }
+
+    /// Arm the next event `delta_ns` nanoseconds from now.
+    fn program_event(&self, delta_ns: u64) -> ClockEventResult;
+}
\ No newline at end of file
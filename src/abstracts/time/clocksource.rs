@@ -0,0 +1,29 @@
+/// Well known ratings of clock sources, a higher rating is preferred.
+pub mod rating {
+    /// Too coarse or too short to be used unless nothing else exists.
+    pub const POOR: u32 = 100;
+    /// Usable, but slow to read or not synchronized.
+    pub const FAIR: u32 = 200;
+    /// Global counter with a fixed frequency.
+    pub const GOOD: u32 = 300;
+    /// Fast, per-CPU and synchronized counter with a fixed frequency.
+    pub const IDEAL: u32 = 400;
+}
+
+/// A free running counter used to measure time.
+pub trait ClockSource: Send + Sync {
+    /// Name of the counter.
+    fn name(&self) -> &'static str;
+
+    /// Quality of the counter, see [`rating`].
+    fn rating(&self) -> u32;
+
+    /// Frequency of the counter in Hz.
+    fn frequency(&self) -> u64;
+
+    /// Mask of the valid bits of the counter, it wraps around after `mask`.
+    fn mask(&self) -> u64;
+
+    /// Read the current value of the counter.
+    fn read(&self) -> u64;
+}
\ No newline at end of file
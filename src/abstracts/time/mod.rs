@@ -0,0 +1,2 @@
+pub mod clockevent;
+pub mod clocksource;
\ No newline at end of file
@@ -5,10 +5,21 @@ use crate::common::structs::mem::misc::MMUFlags;
 pub enum TrapReason {
     Syscall,
     Interrupt(usize),
+    NonMaskableInterrupt,
     PageFault(VirtualAddress, MMUFlags),
     UndefinedInstruction,
     SoftwareBreakpoint,
     HardwareBreakpoint,
     UnalignedAccess,
+    DivideByZero,
+    GeneralProtection(usize),
+    StackFault(usize),
+    SegmentNotPresent(usize),
+    DoubleFault,
+    MachineCheck,
+    FloatingPoint,
+    SimdFloatingPoint,
+    Virtualization,
+    ControlProtection(usize),
     GernelFault(usize),
 }
\ No newline at end of file
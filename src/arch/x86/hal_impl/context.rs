@@ -0,0 +1,83 @@
+use crate::abstracts::context::ContextHAL;
+use core::arch::global_asm;
+
+/// Callee saved registers are pushed on the stack of the thread itself, only
+/// the stack pointer is kept here. Caller saved registers are already on the
+/// stack at every switch point, and a preempted thread keeps its full
+/// `TrapFrame` on its stack until it returns from the interrupt.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct X86Context {
+    rsp: usize,
+}
+
+/// Registers popped by `__context_switch` on the first switch to a thread,
+/// followed by the return address.
+#[repr(C)]
+struct InitialFrame {
+    r15: usize,
+    r14: usize,
+    r13: usize,
+    r12: usize,
+    rbx: usize,
+    rbp: usize,
+    rip: usize,
+}
+
+global_asm!(
+    r#"
+.global __context_switch
+__context_switch:
+    push rbp
+    push rbx
+    push r12
+    push r13
+    push r14
+    push r15
+    mov [rdi], rsp
+    mov rsp, [rsi]
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+
+.global __thread_trampoline
+__thread_trampoline:
+    mov rdi, r12
+    call rbx
+    ud2
+"#
+);
+
+extern "C" {
+    fn __context_switch(prev: *mut X86Context, next: *const X86Context);
+    fn __thread_trampoline();
+}
+
+pub struct ContextHALImpl;
+impl ContextHAL for ContextHALImpl {
+    type Context = X86Context;
+
+    unsafe fn new_context(stack_top: usize, entry: extern "C" fn(usize) -> !, arg: usize) -> X86Context {
+        // The trampoline runs with the stack 16 bytes aligned, as the ABI
+        // expects right before a call
+        let frame = ((stack_top & !0xf) - core::mem::size_of::<InitialFrame>()) as *mut InitialFrame;
+        frame.write(InitialFrame {
+            r15: 0,
+            r14: 0,
+            r13: 0,
+            r12: arg,
+            rbx: entry as usize,
+            rbp: 0,
+            rip: __thread_trampoline as usize,
+        });
+        X86Context { rsp: frame as usize }
+    }
+
+    unsafe fn switch(prev: *mut X86Context, next: *const X86Context) {
+        __context_switch(prev, next)
+    }
+}
\ No newline at end of file
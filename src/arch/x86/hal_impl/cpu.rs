@@ -0,0 +1,60 @@
+use crate::abstracts::cpu::CpuHAL;
+use crate::arch::x86::time;
+use crate::sys::smp::percpu::PerCpu;
+use core::arch::asm;
+use raw_cpuid::CpuId;
+use x86_64::instructions::port::Port;
+use x86_64::instructions::tables::lidt;
+use x86_64::registers::model_specific::GsBase;
+use x86_64::structures::DescriptorTablePointer;
+use x86_64::VirtAddr;
+
+/// The per-CPU data is reached through GS base. The kernel never runs user
+/// code, so GS is not swapped on kernel entry.
+pub struct CpuHALImpl;
+impl CpuHAL for CpuHALImpl {
+    fn cpu_id() -> usize {
+        let id: usize;
+        unsafe {
+            asm!("mov {}, gs:[{offset}]", out(reg) id, offset = const PerCpu::CPU_ID_OFFSET, options(nostack, preserves_flags, readonly));
+        }
+        id
+    }
+
+    fn cpu_frequency() -> u16 {
+        match CpuId::new().get_processor_frequency_info().map(|info| info.processor_base_frequency()) {
+            Some(mhz) if mhz != 0 => mhz,
+            _ => (time::tsc_frequency() / 1_000_000) as u16,
+        }
+    }
+
+    fn reset() -> ! {
+        unsafe {
+            // Pulse the reset line through the keyboard controller
+            Port::<u8>::new(0x64).write(0xfe);
+            // Triple fault if that did not work
+            lidt(&DescriptorTablePointer { limit: 0, base: VirtAddr::zero() });
+            asm!("int3", options(noreturn));
+        }
+    }
+
+    fn local_base() -> usize {
+        let base: usize;
+        unsafe {
+            asm!("mov {}, gs:[{offset}]", out(reg) base, offset = const PerCpu::SELF_PTR_OFFSET, options(nostack, preserves_flags, readonly));
+        }
+        base
+    }
+
+    fn local_read(offset: usize) -> usize {
+        let value: usize;
+        unsafe {
+            asm!("mov {}, gs:[{}]", out(reg) value, in(reg) offset, options(nostack, preserves_flags, readonly));
+        }
+        value
+    }
+
+    unsafe fn set_local_base(base: usize) {
+        GsBase::write(VirtAddr::new(base as u64));
+    }
+}
\ No newline at end of file
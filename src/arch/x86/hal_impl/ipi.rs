@@ -0,0 +1,45 @@
+use crate::abstracts::interrupt::ipi::{IpiHAL, IpiKind, IpiTarget};
+use crate::arch::x86::interrupts::apic::consts::{APIC_CALL_FUNCTION_INTERRUPT, APIC_RESCHEDULE_INTERRUPT};
+use crate::arch::x86::interrupts::apic::Apic;
+use crate::sys;
+use x2apic::lapic::IpiAllShorthand;
+
+pub struct IpiHALImpl;
+impl IpiHALImpl {
+    fn send_one(cpu: usize, kind: IpiKind) {
+        let dest = sys::smp::apic_id(cpu);
+        let lapic = Apic::lapic();
+        match kind {
+            IpiKind::Fixed(vector) => lapic.send_ipi(vector as u8, dest),
+            IpiKind::Nmi => lapic.send_nmi(dest),
+            IpiKind::Init => lapic.send_init_ipi(dest),
+            IpiKind::Startup(vector) => lapic.send_sipi(vector, dest),
+        }
+    }
+
+    fn send_shorthand(who: IpiAllShorthand, kind: IpiKind) {
+        let lapic = Apic::lapic();
+        match (kind, who) {
+            (IpiKind::Fixed(vector), who) => lapic.send_ipi_all(vector as u8, who),
+            (IpiKind::Nmi, who) => lapic.send_nmi_all(who),
+            // INIT and SIPI only support the all-excluding-self shorthand.
+            (IpiKind::Init, IpiAllShorthand::AllExcludingSelf) => lapic.send_init_ipi_all(),
+            (IpiKind::Startup(vector), IpiAllShorthand::AllExcludingSelf) => lapic.send_sipi_all(vector),
+            (kind, _) => panic!("{:?} can not be sent to the current CPU", kind),
+        }
+    }
+}
+
+impl IpiHAL for IpiHALImpl {
+    const CALL_FUNCTION_VECTOR: usize = APIC_CALL_FUNCTION_INTERRUPT;
+    const RESCHEDULE_VECTOR: usize = APIC_RESCHEDULE_INTERRUPT;
+
+    fn send_ipi(target: IpiTarget, kind: IpiKind) {
+        match target {
+            IpiTarget::Cpu(cpu) => Self::send_one(cpu, kind),
+            IpiTarget::Mask(mask) => mask.iter().for_each(|cpu| Self::send_one(cpu, kind)),
+            IpiTarget::AllButSelf => Self::send_shorthand(IpiAllShorthand::AllExcludingSelf, kind),
+            IpiTarget::All => Self::send_shorthand(IpiAllShorthand::AllIncludingSelf, kind),
+        }
+    }
+}
\ No newline at end of file
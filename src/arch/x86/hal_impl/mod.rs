@@ -1,2 +1,5 @@
+pub mod context;
+pub mod cpu;
 pub mod memory;
+pub mod ipi;
 pub mod trace;
\ No newline at end of file
@@ -1,5 +1,6 @@
-use crate::arch::x86::interrupts;
+use crate::arch::x86::{interrupts, time};
 use crate::devices;
+use crate::sys;
 use crate::sys::init::KernelInit;
 use log::info;
 use x86_64::registers::control::{Cr0, Cr0Flags, Cr4, Cr4Flags};
@@ -8,18 +9,34 @@ use x86_64::registers::xcontrol::{XCr0, XCr0Flags};
 pub struct X86init;
 impl KernelInit for X86init {
     unsafe extern "C" fn secondary_cpu_init(cpu: &limine::smp::Cpu) -> ! {
+        // Per-CPU Data, the CPU id is read from it
+        let id = sys::smp::cpu_of_apic(cpu.lapic_id).expect("CPU not reported by the bootloader");
+        sys::smp::percpu::init(id);
+
         // Initialize Trap Frame
         unsafe {
             trapframe::init();
+            interrupts::nmi::init();
+            interrupts::fault::init();
         }
 
         // APIC Initialization
         interrupts::apic::Apic::init_lapic_ap();
+        sys::smp::set_online();
+
+        // Start Periodic Tick
+        sys::time::tick::start();
+
+        // Adopt the boot context as a kernel thread
+        sys::multitask::thread::scheduler::init_cpu();
 
         info!("Secondary CPU {} Initialized", cpu.id);
         crate::secondary_main()
     }
     fn stage0() {
+        // Per-CPU Data, the bootstrap processor is always CPU 0
+        sys::smp::percpu::init(0);
+
         // Detect CPU Features
         let cpuid = raw_cpuid::CpuId::new();
         if let Some(finfo) = cpuid.get_feature_info() {
@@ -64,6 +81,21 @@ impl KernelInit for X86init {
         // IDT Load
         interrupts::module_init();
 
+        // Register Clock Sources
+        time::module_init();
+
+        // Initialize Time Subsystem
+        sys::time::module_init();
+
+        // Read Wall Clock Time
+        devices::rtc::module_init();
+
+        // Read-Copy-Update
+        sys::sync::rcu::init();
+
+        // Initialize Kernel Threads
+        sys::multitask::module_init();
+
         // Secondary CPU Initialization
         Self::secondary_init();
     }
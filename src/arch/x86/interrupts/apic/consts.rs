@@ -7,8 +7,15 @@ pub const IOAPIC_BASE: usize = 0x20;
 pub const LAPIC_BASE: usize = 0xf0;
 pub const LAPIC_IRQ_RANGE: Range<usize> = 0..16;
 pub const IOAPIC_IRQ_RANGE: Range<usize> = IOAPIC_BASE..LAPIC_BASE;
+/// Base address of message signalled interrupts, the destination APIC ID
+/// goes into bits 12..20.
+pub const MSI_ADDRESS_BASE: u64 = 0xfee0_0000;
+/// CPU vectors owned by the APIC.
+pub const APIC_VECTOR_RANGE: Range<usize> = IOAPIC_BASE..LAPIC_BASE + LAPIC_INTERRUPT_VECTOR_NUM;
 
 pub const APIC_TIMER_INTERRUPT: usize = LAPIC_BASE + 1;
 pub const APIC_ERROR_INTERRUPT: usize = LAPIC_BASE + 2;
 pub const APIC_SPURIOUS_INTERRUPT: usize = LAPIC_BASE + 3;
-pub const APIC_TLB_FLUSH_INTERRUPT: usize = LAPIC_BASE + 4;
\ No newline at end of file
+pub const APIC_TLB_FLUSH_INTERRUPT: usize = LAPIC_BASE + 4;
+pub const APIC_CALL_FUNCTION_INTERRUPT: usize = LAPIC_BASE + 5;
+pub const APIC_RESCHEDULE_INTERRUPT: usize = LAPIC_BASE + 6;
\ No newline at end of file
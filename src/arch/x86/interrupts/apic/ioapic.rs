@@ -6,7 +6,7 @@ use crate::sys;
 use acpi::InterruptModel;
 use alloc::vec::Vec;
 use core::fmt;
-use spin::mutex::Mutex;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
 use x2apic::ioapic::{IoApic as IoApicInner, IrqFlags, IrqMode};
 
 /// An I/O APIC structure.
@@ -21,9 +21,13 @@ pub struct IoApic {
     /// Max entry num of the interrupt redirection table.
     max_entry: u8,
     /// Use `x2apic` crate to help us manipulate IOAPIC.
-    inner: Mutex<IoApicInner>,
+    inner: IrqSpinLock<IoApicInner>,
 }
 
+// Registers are only accessed with `inner` locked.
+unsafe impl Send for IoApic {}
+unsafe impl Sync for IoApic {}
+
 impl IoApic {
     /// Create a new [`IoApic`] from fields parsed from the ACPI table, and
     /// initialize it by disabling all interrupt.
@@ -53,7 +57,7 @@ impl IoApic {
             id,
             gsi_start,
             max_entry,
-            inner: Mutex::new(inner),
+            inner: IrqSpinLock::new(inner),
         }
     }
 
@@ -97,7 +101,7 @@ impl IoApic {
         entry.set_dest(dest);
 
         let mut flags = IrqFlags::MASKED; // destination mode: physical
-        if matches!(tm, IrqTriggerMode::Edge) {
+        if matches!(tm, IrqTriggerMode::Level) {
             flags |= IrqFlags::LEVEL_TRIGGERED;
         }
         if matches!(pol, IrqPolarity::ActiveLow) {
@@ -107,6 +111,52 @@ impl IoApic {
 
         unsafe { inner.set_table_entry(idx, entry) };
     }
+
+    /// Set the delivery mode and destination of the `gsi` in redirection
+    /// table, `dest` is a logical destination if `logical` is set.
+    pub fn set_destination(&self, gsi: u32, mode: IrqMode, logical: bool, dest: u8) {
+        let idx = (gsi - self.gsi_start) as u8;
+        let mut inner = self.inner.lock();
+        let mut entry = unsafe { inner.table_entry(idx) };
+        entry.set_mode(mode);
+        entry.set_dest(dest);
+        let mut flags = entry.flags();
+        flags.set(IrqFlags::LOGICAL_DEST, logical);
+        entry.set_flags(flags);
+        unsafe { inner.set_table_entry(idx, entry) };
+    }
+
+    /// Get the destination of the `gsi` from redirection table, along with
+    /// whether it is a logical destination.
+    pub fn destination(&self, gsi: u32) -> (u8, bool) {
+        let idx = (gsi - self.gsi_start) as u8;
+        let entry = unsafe { self.inner.lock().table_entry(idx) };
+        (entry.dest(), entry.flags().contains(IrqFlags::LOGICAL_DEST))
+    }
+
+    /// GSI, vector, trigger mode and polarity of every entry mapped to a
+    /// vector in the redirection table.
+    pub fn routes(&self) -> Vec<(u32, u8, IrqTriggerMode, IrqPolarity)> {
+        let mut inner = self.inner.lock();
+        (0..self.max_entry + 1)
+            .map(|i| (i, unsafe { inner.table_entry(i) }))
+            .filter(|(_, entry)| entry.vector() != 0)
+            .map(|(i, entry)| {
+                let flags = entry.flags();
+                let tm = if flags.contains(IrqFlags::LEVEL_TRIGGERED) {
+                    IrqTriggerMode::Level
+                } else {
+                    IrqTriggerMode::Edge
+                };
+                let pol = if flags.contains(IrqFlags::LOW_ACTIVE) {
+                    IrqPolarity::ActiveLow
+                } else {
+                    IrqPolarity::ActiveHigh
+                };
+                (self.gsi_start + i as u32, entry.vector(), tm, pol)
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -145,6 +195,11 @@ impl IoApicList {
             .iter()
             .find(|i| i.gsi_start <= gsi && gsi <= i.gsi_start + i.max_entry as u32)
     }
+
+    /// Routes of all I/O APICs, see [`IoApic::routes`].
+    pub fn routes(&self) -> Vec<(u32, u8, IrqTriggerMode, IrqPolarity)> {
+        self.io_apics.iter().flat_map(|i| i.routes()).collect()
+    }
 }
 
 impl fmt::Debug for IoApic {
@@ -1,13 +1,23 @@
 use super::consts::{APIC_ERROR_INTERRUPT, APIC_SPURIOUS_INTERRUPT, APIC_TIMER_INTERRUPT};
 use crate::abstracts::memory::address::AddressSpaceHAL;
 use crate::sys;
-use x2apic::lapic::{xapic_base, LocalApic as LocalApicInner, LocalApicBuilder, TimerDivide, TimerMode};
+use crate::sys::smp::CpuMask;
+use raw_cpuid::CpuId;
+use x2apic::lapic::{xapic_base, IpiAllShorthand, LocalApic as LocalApicInner, LocalApicBuilder, TimerDivide, TimerMode};
+use x86::msr::{rdmsr, wrmsr, IA32_TSC_DEADLINE, IA32_X2APIC_CUR_COUNT};
+
+const XAPIC_LDR_OFFSET: usize = 0xd0;
+const XAPIC_DFR_OFFSET: usize = 0xe0;
+const XAPIC_TIMER_CURRENT_OFFSET: usize = 0x390;
+/// Number of CPUs addressable with the flat logical destination model.
+const FLAT_LOGICAL_CPU_NUM: usize = 8;
 
 static mut LAPIC: Option<LocalApic> = None;
 static mut BSP_ID: Option<u8> = None;
 
 pub struct LocalApic {
     inner: LocalApicInner,
+    base_vaddr: usize,
 }
 
 impl LocalApic {
@@ -25,14 +35,49 @@ impl LocalApic {
             .build()
             .unwrap_or_else(|err| panic!("Failed to initialize Local APIC: {:?}", err));
         inner.enable();
+        // The timer stays masked until a clock event mode is chosen
+        inner.disable_timer();
 
         assert!(inner.is_bsp());
         BSP_ID = Some((inner.id() >> 24) as u8);
-        LAPIC = Some(LocalApic { inner });
+        LAPIC = Some(LocalApic { inner, base_vaddr });
+        Self::get().init_logical_id();
     }
 
     pub unsafe fn init_ap() {
-        Self::get().inner.enable();
+        let lapic = Self::get();
+        lapic.inner.enable();
+        lapic.inner.disable_timer();
+        lapic.init_logical_id();
+    }
+
+    fn is_x2apic() -> bool {
+        CpuId::new().get_feature_info().map_or(false, |finfo| finfo.has_x2apic())
+    }
+
+    /// Give the current CPU the flat logical id `1 << cpu`.
+    ///
+    /// In x2APIC mode the logical id is assigned by hardware in cluster model,
+    /// which the 8-bit destination of the I/O APIC can not address, and only
+    /// the first 8 CPUs fit in the flat model.
+    fn init_logical_id(&mut self) {
+        let cpu = sys::smp::current_id();
+        if Self::is_x2apic() || cpu >= FLAT_LOGICAL_CPU_NUM {
+            return;
+        }
+        unsafe {
+            core::ptr::write_volatile((self.base_vaddr + XAPIC_DFR_OFFSET) as *mut u32, u32::MAX);
+            core::ptr::write_volatile((self.base_vaddr + XAPIC_LDR_OFFSET) as *mut u32, (1 << cpu) << 24);
+        }
+    }
+
+    /// Flat logical destination addressing every CPU in `mask`, if any.
+    pub fn logical_destination(mask: CpuMask) -> Option<u8> {
+        if Self::is_x2apic() || mask.iter().any(|cpu| cpu >= FLAT_LOGICAL_CPU_NUM) {
+            None
+        } else {
+            Some(mask.bits() as u8)
+        }
     }
 
     pub fn bsp_id() -> u8 {
@@ -66,4 +111,49 @@ impl LocalApic {
     pub fn set_timer_initial(&mut self, initial: u32) {
         unsafe { self.inner.set_timer_initial(initial) }
     }
+
+    pub fn timer_current(&self) -> u32 {
+        if Self::is_x2apic() {
+            unsafe { rdmsr(IA32_X2APIC_CUR_COUNT) as u32 }
+        } else {
+            unsafe { core::ptr::read_volatile((self.base_vaddr + XAPIC_TIMER_CURRENT_OFFSET) as *const u32) }
+        }
+    }
+
+    /// Arm the timer in TSC-deadline mode, writing 0 disarms it.
+    pub fn set_tsc_deadline(&mut self, deadline: u64) {
+        unsafe { wrmsr(IA32_TSC_DEADLINE, deadline) }
+    }
+
+    pub fn send_ipi(&mut self, vector: u8, dest: u32) {
+        unsafe { self.inner.send_ipi(vector, dest) }
+    }
+
+    pub fn send_ipi_all(&mut self, vector: u8, who: IpiAllShorthand) {
+        unsafe { self.inner.send_ipi_all(vector, who) }
+    }
+
+    pub fn send_nmi(&mut self, dest: u32) {
+        unsafe { self.inner.send_nmi(dest) }
+    }
+
+    pub fn send_nmi_all(&mut self, who: IpiAllShorthand) {
+        unsafe { self.inner.send_nmi_all(who) }
+    }
+
+    pub fn send_init_ipi(&mut self, dest: u32) {
+        unsafe { self.inner.send_init_ipi(dest) }
+    }
+
+    pub fn send_init_ipi_all(&mut self) {
+        unsafe { self.inner.send_init_ipi_all() }
+    }
+
+    pub fn send_sipi(&mut self, vector: u8, dest: u32) {
+        unsafe { self.inner.send_sipi(vector, dest) }
+    }
+
+    pub fn send_sipi_all(&mut self, vector: u8) {
+        unsafe { self.inner.send_sipi_all(vector) }
+    }
 }
\ No newline at end of file
@@ -1,30 +1,38 @@
-use crate::abstracts::interrupt::controller::{InterruptController, IrqHandler, IrqPolarity, IrqTriggerMode};
-use crate::arch::x86::interrupts::apic::consts::{IOAPIC_INTERRUPT_VECTOR_NUM, IOAPIC_IRQ_RANGE, LAPIC_BASE, LAPIC_INTERRUPT_VECTOR_NUM, LAPIC_IRQ_RANGE};
+use crate::abstracts::interrupt::controller::{InterruptController, IrqAffinity, IrqHandler, IrqPolarity, IrqTriggerMode, MsiMessage};
+use crate::arch::x86::interrupts::apic::consts::{APIC_SPURIOUS_INTERRUPT, IOAPIC_INTERRUPT_VECTOR_NUM, IOAPIC_IRQ_RANGE, LAPIC_BASE, LAPIC_INTERRUPT_VECTOR_NUM, LAPIC_IRQ_RANGE, MSI_ADDRESS_BASE};
 use crate::arch::x86::interrupts::apic::ioapic::IoApicList;
 use crate::arch::x86::interrupts::apic::lapic::LocalApic;
 use crate::common::structs::interrupt::manager::{IrqError, IrqManager, IrqResult};
+use crate::common::structs::interrupt::stats::{IrqInfo, IrqInventory};
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
 use alloc::vec;
 use core::arch::asm;
+use core::sync::atomic::{AtomicU64, Ordering};
 use log::error;
-use spin::Mutex;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use x2apic::ioapic::IrqMode;
 
 // pub mod apic;
 mod lapic;
-mod consts;
+pub mod consts;
 mod ioapic;
+pub mod timer;
 
 pub struct Apic {
     io_apic_list: IoApicList,
-    manager_ioapic: Mutex<IrqManager<{ IOAPIC_INTERRUPT_VECTOR_NUM }>>,
-    manager_lapic: Mutex<IrqManager<{ LAPIC_INTERRUPT_VECTOR_NUM }>>,
+    manager_ioapic: IrqSpinLock<IrqManager<{ IOAPIC_INTERRUPT_VECTOR_NUM }>>,
+    manager_lapic: IrqSpinLock<IrqManager<{ LAPIC_INTERRUPT_VECTOR_NUM }>>,
+    spurious: [AtomicU64; KERNEL_MAX_CPU_NUM],
 }
 
 impl Apic {
     pub fn new() -> Self {
         Self {
             io_apic_list: IoApicList::new(),
-            manager_ioapic: Mutex::new(IrqManager::new(IOAPIC_IRQ_RANGE)),
-            manager_lapic: Mutex::new(IrqManager::new(LAPIC_IRQ_RANGE)),
+            manager_ioapic: IrqSpinLock::new(IrqManager::new(IOAPIC_IRQ_RANGE)),
+            manager_lapic: IrqSpinLock::new(IrqManager::new(LAPIC_IRQ_RANGE)),
+            spurious: [const { AtomicU64::new(0) }; KERNEL_MAX_CPU_NUM],
         }
     }
 
@@ -52,9 +60,9 @@ impl Apic {
         unsafe { lapic::LocalApic::get() }
     }
 
-    pub fn register_lapic_handler(&self, vector: usize, handler: IrqHandler) -> IrqResult {
+    pub fn register_lapic_handler(&self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult {
         if vector >= LAPIC_BASE {
-            self.manager_lapic.lock().register_handler(vector - LAPIC_BASE, handler)?;
+            self.manager_lapic.lock().register_handler(vector - LAPIC_BASE, name, handler)?;
             Ok(())
         } else {
             error!("Invalid LAPIC interrupt vector: {}", vector);
@@ -85,6 +93,13 @@ impl InterruptController for Apic {
         })
     }
 
+    /// `sti` only takes effect after the next instruction, so an interrupt
+    /// pending while interrupts were disabled ends the `hlt` instead of being
+    /// taken before it.
+    fn wait_for_interrupt(&self) {
+        unsafe { asm!("sti; hlt") };
+    }
+
     fn enable_interrupt(&self) -> IrqResult {
         unsafe { asm!("sti") };
         Ok(())
@@ -95,7 +110,6 @@ impl InterruptController for Apic {
         Ok(())
     }
 
-    // TODO: Verify this
     fn is_interrupt_enabled(&self) -> bool {
         let mut rflags: usize = 0;
         unsafe {
@@ -105,7 +119,7 @@ impl InterruptController for Apic {
             out(reg) rflags
             )
         }
-        rflags & 0x200 == 0
+        rflags & 0x200 != 0
     }
 
     fn mask_irq(&self, vector: usize) -> IrqResult {
@@ -124,11 +138,11 @@ impl InterruptController for Apic {
         })
     }
 
-    fn register_irq_handler(&self, vector: usize, handler: IrqHandler) -> IrqResult {
+    fn register_irq_handler(&self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult {
         let gsi = vector as u32;
         self.with_ioapic(gsi, |apic| {
             let vector = apic.get_vector(gsi) as _;
-            let vector = self.manager_ioapic.lock().register_handler(vector, handler)? as u8;
+            let vector = self.manager_ioapic.lock().register_handler(vector, name, handler)? as u8;
             apic.map_vector(gsi, vector);
             Ok(())
         })
@@ -145,6 +159,12 @@ impl InterruptController for Apic {
     }
 
     fn handle_irq(&self, vector: usize) -> IrqResult {
+        // Spurious interrupts must not be acknowledged
+        if vector == APIC_SPURIOUS_INTERRUPT {
+            self.spurious[sys::smp::current_id()].fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
         Self::lapic().eoi();
         let result = if vector >= LAPIC_BASE {
             self.manager_lapic.lock().handle_irq(vector - LAPIC_BASE)
@@ -166,8 +186,102 @@ impl InterruptController for Apic {
         }
     }
 
-    // TODO: Implement this with common HAL
-    // fn apic_timer_enable(&self) {
-    //     Self::lapic().enable_timer();
-    // }
+    fn set_affinity(&self, vector: usize, affinity: IrqAffinity) -> IrqResult {
+        let gsi = vector as u32;
+        self.with_ioapic(gsi, |ioapic| {
+            match affinity {
+                IrqAffinity::Cpu(cpu) => {
+                    if !sys::smp::is_online(cpu) {
+                        error!("Can not route GSI {} to offline CPU {}", gsi, cpu);
+                        return Err(IrqError::InvalidCpu);
+                    }
+                    // Physical destinations only hold an 8-bit APIC ID
+                    let Ok(apic_id) = u8::try_from(sys::smp::apic_id(cpu)) else {
+                        error!("Can not route GSI {} to CPU {} with APIC ID above 255", gsi, cpu);
+                        return Err(IrqError::InvalidCpu);
+                    };
+                    ioapic.set_destination(gsi, IrqMode::Fixed, false, apic_id);
+                }
+                IrqAffinity::LowestPriority => {
+                    let dest = LocalApic::logical_destination(sys::smp::online_mask())
+                        .ok_or(IrqError::NotSupported)?;
+                    ioapic.set_destination(gsi, IrqMode::LowestPriority, true, dest);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn affinity(&self, vector: usize) -> IrqResult<IrqAffinity> {
+        let gsi = vector as u32;
+        let ioapic = self.io_apic_list.find(gsi).ok_or(IrqError::InvalidIrqVector)?;
+        match ioapic.destination(gsi) {
+            (_, true) => Ok(IrqAffinity::LowestPriority),
+            (dest, false) => sys::smp::cpu_of_apic(dest as u32)
+                .map(IrqAffinity::Cpu)
+                .ok_or(IrqError::InvalidCpu),
+        }
+    }
+
+    fn supports_msi(&self) -> bool {
+        true
+    }
+
+    fn register_msi_handler(&self, cpu: usize, name: &'static str, handler: IrqHandler) -> IrqResult<MsiMessage> {
+        if !sys::smp::is_online(cpu) {
+            return Err(IrqError::InvalidCpu);
+        }
+        // Without interrupt remapping the address only holds an 8-bit APIC ID
+        let apic_id = sys::smp::apic_id(cpu);
+        if apic_id > u8::MAX as u32 {
+            return Err(IrqError::NotSupported);
+        }
+        let vector = self.manager_ioapic.lock().register_handler(0, name, handler)?;
+        Ok(MsiMessage {
+            address: MSI_ADDRESS_BASE | (apic_id as u64) << 12,
+            // Fixed delivery, edge triggered
+            data: vector as u32,
+        })
+    }
+
+    fn unregister_msi_handler(&self, msg: MsiMessage) -> IrqResult {
+        self.manager_ioapic.lock().unregister_handler((msg.data & 0xff) as usize)
+    }
+
+    fn inventory(&self) -> IrqInventory {
+        let cpus = sys::smp::cpus();
+        let routes = self.io_apic_list.routes();
+        let mut irqs = vec![];
+
+        let manager_ioapic = self.manager_ioapic.lock();
+        for (vector, owner) in manager_ioapic.registered() {
+            let route = routes.iter().find(|(_, v, _, _)| *v as usize == vector);
+            irqs.push(IrqInfo {
+                vector,
+                gsi: route.map(|(gsi, _, _, _)| *gsi),
+                trigger: route.map(|(_, _, tm, _)| *tm),
+                polarity: route.map(|(_, _, _, pol)| *pol),
+                owner,
+                counts: cpus.clone().map(|cpu| manager_ioapic.count(cpu, vector)).collect(),
+            });
+        }
+
+        let manager_lapic = self.manager_lapic.lock();
+        for (idx, owner) in manager_lapic.registered() {
+            irqs.push(IrqInfo {
+                vector: idx + LAPIC_BASE,
+                gsi: None,
+                trigger: None,
+                polarity: None,
+                owner,
+                counts: cpus.clone().map(|cpu| manager_lapic.count(cpu, idx)).collect(),
+            });
+        }
+
+        IrqInventory {
+            irqs,
+            spurious: cpus.clone().map(|cpu| self.spurious[cpu].load(Ordering::Relaxed)).collect(),
+            unhandled: cpus.map(|cpu| manager_ioapic.unhandled(cpu) + manager_lapic.unhandled(cpu)).collect(),
+        }
+    }
 }
\ No newline at end of file
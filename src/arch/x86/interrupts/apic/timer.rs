@@ -0,0 +1,169 @@
+use super::Apic;
+use crate::abstracts::time::clockevent::{ClockEventDevice, ClockEventError, ClockEventFeatures, ClockEventMode, ClockEventResult};
+use crate::devices::pit;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use core::arch::x86_64::_rdtsc;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use log::info;
+use raw_cpuid::CpuId;
+use x2apic::lapic::{TimerDivide, TimerMode};
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+const CALIBRATION_US: u64 = 10_000;
+const MIN_DELTA_TICKS: u64 = 16;
+
+/// Frequency of the timer with divide configuration 16, in Hz.
+static TIMER_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+/// Frequency of the time stamp counter, in Hz.
+static TSC_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+/// Current mode of the timer on each CPU.
+static MODES: [AtomicU8; KERNEL_MAX_CPU_NUM] = [const { AtomicU8::new(ClockEventMode::Shutdown as u8) }; KERNEL_MAX_CPU_NUM];
+
+/// The local APIC timer, one instance per CPU.
+pub struct LapicTimer;
+
+impl LapicTimer {
+    /// Measure the frequency of the timer and the TSC against the PIT.
+    ///
+    /// All CPUs are assumed to share the same bus clock, so this only needs to
+    /// run once on the BSP.
+    pub fn calibrate() {
+        let lapic = Apic::lapic();
+        lapic.set_timer_divide(TimerDivide::Div16);
+        lapic.set_timer_mode(TimerMode::OneShot);
+
+        lapic.set_timer_initial(u32::MAX);
+        let tsc_start = unsafe { _rdtsc() };
+        pit::busy_wait_us(CALIBRATION_US);
+        let remaining = lapic.timer_current();
+        let tsc_end = unsafe { _rdtsc() };
+        lapic.set_timer_initial(0);
+
+        let ticks = (u32::MAX - remaining) as u64;
+        let timer_frequency = ticks * 1_000_000 / CALIBRATION_US;
+        let tsc_frequency = (tsc_end - tsc_start) * 1_000_000 / CALIBRATION_US;
+        TIMER_FREQUENCY.store(timer_frequency, Ordering::Release);
+        TSC_FREQUENCY.store(tsc_frequency, Ordering::Release);
+        info!("Local APIC timer frequency: {} Hz, TSC frequency: {} Hz", timer_frequency, tsc_frequency);
+    }
+
+    pub fn tsc_frequency() -> u64 {
+        TSC_FREQUENCY.load(Ordering::Acquire)
+    }
+
+    fn has_tsc_deadline() -> bool {
+        CpuId::new().get_feature_info().map_or(false, |finfo| finfo.has_tsc_deadline())
+    }
+
+    fn frequency() -> ClockEventResult<u64> {
+        match TIMER_FREQUENCY.load(Ordering::Acquire) {
+            0 => Err(ClockEventError::NotCalibrated),
+            frequency => Ok(frequency),
+        }
+    }
+
+    fn ns_to_ticks(ns: u64, frequency: u64) -> u64 {
+        (ns as u128 * frequency as u128 / NSEC_PER_SEC as u128) as u64
+    }
+
+    fn ticks_to_ns(ticks: u64, frequency: u64) -> u64 {
+        (ticks as u128 * NSEC_PER_SEC as u128 / frequency as u128) as u64
+    }
+
+    fn set_current_mode(mode: ClockEventMode) {
+        MODES[sys::smp::current_id()].store(mode as u8, Ordering::Relaxed);
+    }
+}
+
+impl ClockEventDevice for LapicTimer {
+    fn name(&self) -> &'static str {
+        "lapic-timer"
+    }
+
+    fn features(&self) -> ClockEventFeatures {
+        let mut features = ClockEventFeatures::PERIODIC | ClockEventFeatures::ONESHOT | ClockEventFeatures::PER_CPU;
+        if Self::has_tsc_deadline() {
+            features |= ClockEventFeatures::DEADLINE;
+        }
+        features
+    }
+
+    fn min_delta_ns(&self) -> u64 {
+        Self::frequency().map_or(0, |frequency| Self::ticks_to_ns(MIN_DELTA_TICKS, frequency))
+    }
+
+    fn max_delta_ns(&self) -> u64 {
+        Self::frequency().map_or(0, |frequency| Self::ticks_to_ns(u32::MAX as u64, frequency))
+    }
+
+    fn mode(&self) -> ClockEventMode {
+        match MODES[sys::smp::current_id()].load(Ordering::Relaxed) {
+            mode if mode == ClockEventMode::Periodic as u8 => ClockEventMode::Periodic,
+            mode if mode == ClockEventMode::OneShot as u8 => ClockEventMode::OneShot,
+            mode if mode == ClockEventMode::Deadline as u8 => ClockEventMode::Deadline,
+            _ => ClockEventMode::Shutdown,
+        }
+    }
+
+    fn shutdown(&self) -> ClockEventResult {
+        let lapic = Apic::lapic();
+        lapic.disable_timer();
+        lapic.set_timer_initial(0);
+        Self::set_current_mode(ClockEventMode::Shutdown);
+        Ok(())
+    }
+
+    fn set_periodic(&self, period_ns: u64) -> ClockEventResult {
+        let ticks = Self::ns_to_ticks(period_ns, Self::frequency()?).clamp(MIN_DELTA_TICKS, u32::MAX as u64);
+        let lapic = Apic::lapic();
+        lapic.set_timer_divide(TimerDivide::Div16);
+        lapic.set_timer_mode(TimerMode::Periodic);
+        lapic.set_timer_initial(ticks as u32);
+        lapic.enable_timer();
+        Self::set_current_mode(ClockEventMode::Periodic);
+        Ok(())
+    }
+
+    fn set_oneshot(&self) -> ClockEventResult {
+        Self::frequency()?;
+        let lapic = Apic::lapic();
+        lapic.set_timer_divide(TimerDivide::Div16);
+        lapic.set_timer_mode(TimerMode::OneShot);
+        lapic.set_timer_initial(0);
+        lapic.enable_timer();
+        Self::set_current_mode(ClockEventMode::OneShot);
+        Ok(())
+    }
+
+    fn set_deadline(&self) -> ClockEventResult {
+        if !Self::has_tsc_deadline() {
+            return Err(ClockEventError::NotSupported);
+        }
+        if Self::tsc_frequency() == 0 {
+            return Err(ClockEventError::NotCalibrated);
+        }
+        let lapic = Apic::lapic();
+        lapic.set_timer_mode(TimerMode::TscDeadline);
+        lapic.set_tsc_deadline(0);
+        lapic.enable_timer();
+        Self::set_current_mode(ClockEventMode::Deadline);
+        Ok(())
+    }
+
+    fn program_event(&self, delta_ns: u64) -> ClockEventResult {
+        let lapic = Apic::lapic();
+        match self.mode() {
+            ClockEventMode::OneShot => {
+                let ticks = Self::ns_to_ticks(delta_ns, Self::frequency()?).clamp(MIN_DELTA_TICKS, u32::MAX as u64);
+                lapic.set_timer_initial(ticks as u32);
+            }
+            ClockEventMode::Deadline => {
+                let delta = Self::ns_to_ticks(delta_ns, Self::tsc_frequency()).max(MIN_DELTA_TICKS);
+                lapic.set_tsc_deadline(unsafe { _rdtsc() } + delta);
+            }
+            _ => return Err(ClockEventError::InvalidMode),
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
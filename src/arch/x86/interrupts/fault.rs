@@ -0,0 +1,206 @@
+use crate::abstracts::memory::address::AddressSpaceHAL;
+use crate::abstracts::memory::vm::VmHAL;
+use crate::abstracts::trap::TrapReason;
+use crate::arch::x86::interrupts::ist;
+use crate::common::debug::symbols::KERNEL_SYMBOLS;
+use crate::sys;
+use alloc::string::String;
+use core::arch::asm;
+use core::fmt;
+use core::fmt::Write;
+use raw_cpuid::CpuId;
+use trapframe::TrapFrame;
+use x86::msr::rdmsr;
+use x86_64::structures::paging::{OffsetPageTable, PageTable, Translate};
+use x86_64::VirtAddr;
+
+pub const STACK_SEGMENT_FAULT_VECTOR: u8 = 12;
+pub const CONTROL_PROTECTION_VECTOR: u8 = 21;
+
+const IA32_MCG_CAP: u32 = 0x179;
+const IA32_MCG_STATUS: u32 = 0x17a;
+const IA32_MC0_STATUS: u32 = 0x401;
+const IA32_MC0_ADDR: u32 = 0x402;
+const IA32_MC0_MISC: u32 = 0x403;
+const MCI_STATUS_VAL: u64 = 1 << 63;
+const MCI_STATUS_UC: u64 = 1 << 61;
+const MCI_STATUS_MISCV: u64 = 1 << 59;
+const MCI_STATUS_ADDRV: u64 = 1 << 58;
+
+/// Number of instruction bytes dumped at the faulting RIP.
+const INSTRUCTION_DUMP_LEN: usize = 16;
+const KERNEL_SPACE_START: usize = 0xffff_8000_0000_0000;
+const PAGE_SIZE: usize = 0x1000;
+
+/// Exception flags shared by the x87 status word and MXCSR.
+const FP_EXCEPTION_FLAGS: [&str; 6] = ["invalid", "denormal", "divide-by-zero", "overflow", "underflow", "precision"];
+
+/// Give the double fault and machine check gates of the current CPU their own
+/// IST stacks.
+///
+/// A double fault is usually caused by a kernel stack overflow, and a machine
+/// check may arrive at any instruction, so neither may trust the current stack.
+pub unsafe fn init() {
+    ist::set_stack(x86::irq::DOUBLE_FAULT_VECTOR, ist::DOUBLE_FAULT_IST_INDEX);
+    ist::set_stack(x86::irq::MACHINE_CHECK_VECTOR, ist::MACHINE_CHECK_IST_INDEX);
+}
+
+/// Panic with a decoded report of the fatal exception `reason`.
+pub fn report(reason: TrapReason, tf: &TrapFrame) -> ! {
+    let mut report = String::new();
+    let _ = write_report(&mut report, &reason, tf);
+    panic!("{}", report)
+}
+
+fn write_report(w: &mut String, reason: &TrapReason, tf: &TrapFrame) -> fmt::Result {
+    writeln!(
+        w,
+        "{} (vector {:#x}, error code {:#x}) @ CPU{}",
+        name(reason),
+        tf.trap_num,
+        tf.error_code,
+        sys::smp::current_id()
+    )?;
+    match *reason {
+        TrapReason::GeneralProtection(code) | TrapReason::StackFault(code) | TrapReason::SegmentNotPresent(code) => {
+            write_selector(w, code)?
+        }
+        TrapReason::ControlProtection(code) => write_control_protection(w, code)?,
+        TrapReason::FloatingPoint => write_fpu_status(w)?,
+        TrapReason::SimdFloatingPoint => write_mxcsr(w)?,
+        TrapReason::MachineCheck => write_machine_check(w)?,
+        _ => {}
+    }
+
+    match KERNEL_SYMBOLS.find_symbol(tf.rip) {
+        Some((function_name, offset)) => writeln!(w, "RIP: <{:#x}> - <{:#} + {:#x}>", tf.rip, function_name, offset)?,
+        None => writeln!(w, "RIP: <{:#x}> - <? + ?>", tf.rip)?,
+    }
+    write_instruction_bytes(w, tf.rip)?;
+    write!(w, "{:#x?}", tf)
+}
+
+fn name(reason: &TrapReason) -> &'static str {
+    match reason {
+        TrapReason::DivideByZero => "Divide error (#DE)",
+        TrapReason::GeneralProtection(_) => "General protection fault (#GP)",
+        TrapReason::StackFault(_) => "Stack-segment fault (#SS)",
+        TrapReason::SegmentNotPresent(_) => "Segment not present (#NP)",
+        TrapReason::DoubleFault => "Double fault (#DF)",
+        TrapReason::MachineCheck => "Machine check (#MC)",
+        TrapReason::FloatingPoint => "x87 floating-point exception (#MF)",
+        TrapReason::SimdFloatingPoint => "SIMD floating-point exception (#XM)",
+        TrapReason::Virtualization => "Virtualization exception (#VE)",
+        TrapReason::ControlProtection(_) => "Control protection exception (#CP)",
+        _ => "Exception",
+    }
+}
+
+/// Decode the selector error code of #GP, #SS and #NP.
+fn write_selector(w: &mut String, code: usize) -> fmt::Result {
+    if code == 0 {
+        return writeln!(w, "Selector: none");
+    }
+    let table = if code & 0b10 != 0 {
+        "IDT"
+    } else if code & 0b100 != 0 {
+        "LDT"
+    } else {
+        "GDT"
+    };
+    let external = if code & 0b1 != 0 { ", external event" } else { "" };
+    writeln!(w, "Selector: {} index {:#x}{}", table, (code >> 3) & 0x1fff, external)
+}
+
+fn write_control_protection(w: &mut String, code: usize) -> fmt::Result {
+    let kind = match code & 0x7fff {
+        1 => "near RET",
+        2 => "far RET/IRET",
+        3 => "missing ENDBRANCH",
+        4 => "RSTORSSP",
+        5 => "SETSSBSY",
+        _ => "unknown",
+    };
+    let enclave = if code & (1 << 15) != 0 { ", in enclave" } else { "" };
+    writeln!(w, "Cause: {}{}", kind, enclave)
+}
+
+fn write_fp_flags(w: &mut String, flags: u32) -> fmt::Result {
+    for (bit, flag) in FP_EXCEPTION_FLAGS.iter().enumerate() {
+        if flags & (1 << bit) != 0 {
+            write!(w, " {}", flag)?;
+        }
+    }
+    writeln!(w)
+}
+
+fn write_fpu_status(w: &mut String) -> fmt::Result {
+    let status: u16;
+    unsafe {
+        asm!("fnstsw ax", out("ax") status, options(nomem, nostack, preserves_flags));
+    }
+    write!(w, "FPU status: {:#06x}, stack fault: {}, flags:", status, status & (1 << 6) != 0)?;
+    write_fp_flags(w, status as u32)
+}
+
+fn write_mxcsr(w: &mut String) -> fmt::Result {
+    let mut mxcsr: u32 = 0;
+    unsafe {
+        asm!("stmxcsr [{}]", in(reg) &mut mxcsr, options(nostack, preserves_flags));
+    }
+    write!(w, "MXCSR: {:#010x}, flags:", mxcsr)?;
+    write_fp_flags(w, mxcsr)
+}
+
+fn write_machine_check(w: &mut String) -> fmt::Result {
+    if !CpuId::new().get_feature_info().map_or(false, |finfo| finfo.has_mca()) {
+        return writeln!(w, "Machine check architecture not supported");
+    }
+    let (cap, status) = unsafe { (rdmsr(IA32_MCG_CAP), rdmsr(IA32_MCG_STATUS)) };
+    writeln!(w, "MCG_STATUS: {:#x}", status)?;
+    for bank in 0..(cap & 0xff) as u32 {
+        let status = unsafe { rdmsr(IA32_MC0_STATUS + bank * 4) };
+        if status & MCI_STATUS_VAL == 0 {
+            continue;
+        }
+        write!(w, "Bank {}: status {:#018x}", bank, status)?;
+        if status & MCI_STATUS_UC != 0 {
+            write!(w, " uncorrected")?;
+        }
+        if status & MCI_STATUS_ADDRV != 0 {
+            write!(w, ", addr {:#x}", unsafe { rdmsr(IA32_MC0_ADDR + bank * 4) })?;
+        }
+        if status & MCI_STATUS_MISCV != 0 {
+            write!(w, ", misc {:#x}", unsafe { rdmsr(IA32_MC0_MISC + bank * 4) })?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// Whether `addr` is mapped in the active page table, walked without
+/// touching any unmapped memory.
+fn is_mapped(addr: usize) -> bool {
+    let offset = VirtAddr::new(sys::mem::address_space::phys_to_virt(0) as u64);
+    let root = sys::mem::address_space::phys_to_virt(sys::mem::vmm::current_addr()) as *mut PageTable;
+    let table = unsafe { OffsetPageTable::new(&mut *root, offset) };
+    table.translate_addr(VirtAddr::new(addr as u64)).is_some()
+}
+
+/// Dump the bytes at `rip`, without crossing into a page which may not be
+/// mapped.
+fn write_instruction_bytes(w: &mut String, rip: usize) -> fmt::Result {
+    if rip < KERNEL_SPACE_START {
+        return writeln!(w, "Code: <not in kernel space>");
+    }
+    if !is_mapped(rip) {
+        return writeln!(w, "Code: <not mapped>");
+    }
+    let len = INSTRUCTION_DUMP_LEN.min(PAGE_SIZE - rip % PAGE_SIZE);
+    write!(w, "Code:")?;
+    for i in 0..len {
+        let byte = unsafe { core::ptr::read_volatile((rip + i) as *const u8) };
+        write!(w, " {:02x}", byte)?;
+    }
+    writeln!(w)
+}
@@ -0,0 +1,43 @@
+use alloc::vec;
+use core::arch::asm;
+use core::mem::size_of;
+use x86_64::instructions::tables::{sgdt, sidt};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::VirtAddr;
+
+/// Index of the NMI stack in the interrupt stack table.
+pub const NMI_IST_INDEX: usize = 0;
+/// Index of the double fault stack in the interrupt stack table.
+pub const DOUBLE_FAULT_IST_INDEX: usize = 1;
+/// Index of the machine check stack in the interrupt stack table.
+pub const MACHINE_CHECK_IST_INDEX: usize = 2;
+
+const IST_STACK_SIZE: usize = 16 * 1024;
+
+/// Switch the gate `vector` of the current CPU to a fresh stack in IST entry
+/// `index`.
+///
+/// Must be called after `trapframe::init`, which builds a fresh GDT, TSS and
+/// IDT for each CPU.
+pub unsafe fn set_stack(vector: u8, index: usize) {
+    let stack = vec![0u8; IST_STACK_SIZE].leak();
+    let stack_top = VirtAddr::from_ptr(stack.as_ptr_range().end).align_down(16u64);
+    current_tss().interrupt_stack_table[index] = stack_top;
+
+    // Bits 0..3 of the 5th byte of a gate select the IST entry, 0 means none.
+    let idtr = sidt();
+    let gate = idtr.base.as_mut_ptr::<u8>().add(vector as usize * 16);
+    gate.add(4).write_volatile(index as u8 + 1);
+}
+
+/// TSS of the current CPU, located through the task register.
+unsafe fn current_tss() -> &'static mut TaskStateSegment {
+    let selector: u16;
+    asm!("str {0:x}", out(reg) selector, options(nomem, nostack, preserves_flags));
+    let gdtr = sgdt();
+    let desc = gdtr.base.as_ptr::<u64>().add((selector >> 3) as usize);
+    let (low, high) = (*desc, *desc.add(1));
+    let base = ((low >> 16) & 0xff_ffff) | (((low >> 56) & 0xff) << 24) | ((high & 0xffff_ffff) << 32);
+    debug_assert!(((low & 0xffff) as usize) + 1 >= size_of::<TaskStateSegment>());
+    &mut *(base as *mut TaskStateSegment)
+}
@@ -1,16 +1,36 @@
 use crate::sys;
+use alloc::boxed::Box;
 use alloc::sync::Arc;
 
+mod ist;
 mod trap;
 pub mod apic;
+pub mod fault;
+pub mod nmi;
 
 pub fn module_init() {
     unsafe {
         trapframe::init();
+        nmi::init();
+        fault::init();
     }
 
     // Initialize APIC
     apic::Apic::init_lapic_bsp();
     let irq_ctl = Arc::new(apic::Apic::new());
-    sys::interrupt::set_ic(irq_ctl);
+    irq_ctl.register_lapic_handler(apic::consts::APIC_CALL_FUNCTION_INTERRUPT, "call-function", Box::new(|_| {
+        sys::smp::call::handle_ipi();
+    })).unwrap();
+    irq_ctl.register_lapic_handler(apic::consts::APIC_RESCHEDULE_INTERRUPT, "reschedule", Box::new(|_| {
+        sys::multitask::thread::scheduler::request_resched();
+    })).unwrap();
+    irq_ctl.register_lapic_handler(apic::consts::APIC_TIMER_INTERRUPT, "lapic-timer", Box::new(|_| {
+        sys::time::clockevent::handle_event();
+    })).unwrap();
+
+    // Initialize Local APIC Timer
+    apic::timer::LapicTimer::calibrate();
+    sys::time::clockevent::register_device(Arc::new(apic::timer::LapicTimer));
+    sys::interrupt::register_ic("apic", apic::consts::APIC_VECTOR_RANGE, irq_ctl)
+        .expect("Failed to register APIC");
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use crate::arch::x86::interrupts::ist;
+use crate::sys;
+use core::sync::atomic::{AtomicU16, Ordering};
+use log::warn;
+use trapframe::TrapFrame;
+use x86_64::instructions::port::Port;
+
+const NMI_STATUS_PORT: u16 = 0x61;
+const NMI_STATUS_IOCHK: u8 = 1 << 6;
+const NMI_STATUS_SERR: u8 = 1 << 7;
+/// Set in `UNREPORTED` along with the status of the last NMI.
+const NMI_UNREPORTED: u16 = 1 << 8;
+
+crate::per_cpu! {
+    /// Status port value of the last NMI not logged yet. The logger takes a
+    /// lock an NMI may have interrupted, so the NMI handler only records it.
+    static UNREPORTED: AtomicU16 = AtomicU16::new(0);
+}
+
+/// Give the NMI gate of the current CPU its own IST stack.
+///
+/// An NMI may arrive at any instruction, even while the kernel stack is
+/// unusable, so it must not share that stack.
+pub unsafe fn init() {
+    ist::set_stack(x86::irq::NONMASKABLE_INTERRUPT_VECTOR, ist::NMI_IST_INDEX);
+}
+
+pub fn handle_nmi(tf: &TrapFrame) {
+    if sys::smp::stop::handle_nmi(tf) {
+        return;
+    }
+
+    let status = unsafe { Port::<u8>::new(NMI_STATUS_PORT).read() };
+    UNREPORTED.get().store(NMI_UNREPORTED | status as u16, Ordering::Release);
+}
+
+/// Log the NMI recorded on the current CPU, if any. Called outside NMI
+/// context, on return from a hardware interrupt.
+pub fn report_pending() {
+    let unreported = UNREPORTED.get().swap(0, Ordering::AcqRel);
+    if unreported & NMI_UNREPORTED == 0 {
+        return;
+    }
+    let status = unreported as u8;
+    if status & NMI_STATUS_SERR != 0 {
+        warn!("NMI: system bus error @ CPU{}", sys::smp::current_id());
+    } else if status & NMI_STATUS_IOCHK != 0 {
+        warn!("NMI: I/O channel check error @ CPU{}", sys::smp::current_id());
+    } else {
+        warn!("NMI: unknown reason {:#x} @ CPU{}", status, sys::smp::current_id());
+    }
+}
\ No newline at end of file
@@ -1,11 +1,12 @@
 use crate::abstracts::trap::TrapReason;
+use crate::arch::x86::interrupts::fault::{CONTROL_PROTECTION_VECTOR, STACK_SEGMENT_FAULT_VECTOR};
+use crate::arch::x86::interrupts::{fault, nmi};
 use crate::common::structs::interrupt;
 use crate::common::structs::mem::misc::MMUFlags;
 use crate::sys;
 use log::{error, info, trace};
-use raw_cpuid::CpuId;
 use trapframe::TrapFrame;
-use x86::irq::{ALIGNMENT_CHECK_VECTOR, BREAKPOINT_VECTOR, DEBUG_VECTOR, INVALID_OPCODE_VECTOR, PAGE_FAULT_VECTOR};
+use x86::irq::{ALIGNMENT_CHECK_VECTOR, BREAKPOINT_VECTOR, DEBUG_VECTOR, INVALID_OPCODE_VECTOR, NONMASKABLE_INTERRUPT_VECTOR, PAGE_FAULT_VECTOR};
 
 impl TrapReason {
     pub fn from(trap_num: usize, error_code: usize) -> Self {
@@ -19,9 +20,20 @@ impl TrapReason {
         }
         match trap_num as u8 {
             DEBUG_VECTOR => Self::HardwareBreakpoint,
+            NONMASKABLE_INTERRUPT_VECTOR => Self::NonMaskableInterrupt,
             BREAKPOINT_VECTOR => Self::SoftwareBreakpoint,
             INVALID_OPCODE_VECTOR => Self::UndefinedInstruction,
             ALIGNMENT_CHECK_VECTOR => Self::UnalignedAccess,
+            DIVIDE_ERROR_VECTOR => Self::DivideByZero,
+            GENERAL_PROTECTION_FAULT_VECTOR => Self::GeneralProtection(error_code),
+            STACK_SEGMENT_FAULT_VECTOR => Self::StackFault(error_code),
+            SEGMENT_NOT_PRESENT_VECTOR => Self::SegmentNotPresent(error_code),
+            DOUBLE_FAULT_VECTOR => Self::DoubleFault,
+            MACHINE_CHECK_VECTOR => Self::MachineCheck,
+            X87_FPU_VECTOR => Self::FloatingPoint,
+            SIMD_FLOATING_POINT_VECTOR => Self::SimdFloatingPoint,
+            VIRTUALIZATION_VECTOR => Self::Virtualization,
+            CONTROL_PROTECTION_VECTOR => Self::ControlProtection(error_code),
             PAGE_FAULT_VECTOR => {
                 bitflags::bitflags! {
                     struct PageFaultErrorCode: u32 {
@@ -57,9 +69,19 @@ impl TrapReason {
     }
 }
 
+/// Interrupt enable flag of RFLAGS.
+const RFLAGS_IF: usize = 1 << 9;
+
 #[no_mangle]
 pub extern "C" fn trap_handler(tf: &mut TrapFrame) {
-    let cpuid = CpuId::new().get_feature_info().unwrap().initial_local_apic_id();
+    // The NMI may have interrupted a holder of the logger lock, so it is
+    // dispatched before anything logs
+    if tf.trap_num == NONMASKABLE_INTERRUPT_VECTOR as usize {
+        nmi::handle_nmi(tf);
+        return;
+    }
+
+    let cpuid = sys::smp::current_id();
     trace!(
         "Interrupt: {:#x} @ CPU{}",
         tf.trap_num,
@@ -73,8 +95,26 @@ pub extern "C" fn trap_handler(tf: &mut TrapFrame) {
             vaddr, flags, cpuid, tf
         ),
         TrapReason::Interrupt(vector) => {
-            sys::interrupt::get_ic().handle_irq(vector).unwrap()
+            let percpu = sys::smp::percpu::this();
+            percpu.enter_irq();
+            sys::sync::rcu::irq_enter();
+            sys::interrupt::get_ic().handle_irq(vector).unwrap();
+            percpu.leave_irq();
+            nmi::report_pending();
+            sys::interrupt::softirq::irq_exit();
+            sys::sync::rcu::irq_exit();
+            sys::multitask::thread::scheduler::preempt_on_irq_exit(tf.rflags & RFLAGS_IF != 0);
         }
+        reason @ (TrapReason::DivideByZero
+        | TrapReason::GeneralProtection(_)
+        | TrapReason::StackFault(_)
+        | TrapReason::SegmentNotPresent(_)
+        | TrapReason::DoubleFault
+        | TrapReason::MachineCheck
+        | TrapReason::FloatingPoint
+        | TrapReason::SimdFloatingPoint
+        | TrapReason::Virtualization
+        | TrapReason::ControlProtection(_)) => fault::report(reason, tf),
         other => panic!("Unhandled trap {:x?} {:#x?}", other, tf),
     }
 }
\ No newline at end of file
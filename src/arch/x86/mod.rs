@@ -1,6 +1,7 @@
 mod entry;
 mod interrupts;
 mod init;
+mod time;
 pub mod hal_impl;
 
 #[cfg(not(feature = "dwarf-unwind"))]
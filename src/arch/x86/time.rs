@@ -0,0 +1,86 @@
+use crate::abstracts::time::clocksource::{rating, ClockSource};
+use crate::arch::x86::interrupts::apic::timer::LapicTimer;
+use crate::devices;
+use crate::devices::{acpi, pit};
+use crate::sys;
+use alloc::boxed::Box;
+use core::arch::x86_64::_rdtsc;
+use core::sync::atomic::{AtomicU64, Ordering};
+use raw_cpuid::CpuId;
+
+static TSC: Tsc = Tsc { frequency: AtomicU64::new(0) };
+
+/// The time stamp counter of the CPUs.
+pub struct Tsc {
+    frequency: AtomicU64,
+}
+
+impl Tsc {
+    /// Frequency reported by CPUID leaf 0x15, then the base frequency of leaf
+    /// 0x16, falling back to the calibration against the PIT.
+    fn detect_frequency() -> u64 {
+        let cpuid = CpuId::new();
+        if let Some(frequency) = cpuid.get_tsc_info().and_then(|info| info.tsc_frequency()) {
+            return frequency;
+        }
+        match cpuid.get_processor_frequency_info().map(|info| info.processor_base_frequency()) {
+            Some(mhz) if mhz != 0 => mhz as u64 * 1_000_000,
+            _ => LapicTimer::tsc_frequency(),
+        }
+    }
+
+    /// An invariant TSC ticks at a constant rate in all power states.
+    fn is_invariant() -> bool {
+        CpuId::new().get_advanced_power_mgmt_info().map_or(false, |info| info.has_invariant_tsc())
+    }
+}
+
+impl ClockSource for Tsc {
+    fn name(&self) -> &'static str {
+        "tsc"
+    }
+
+    fn rating(&self) -> u32 {
+        if Self::is_invariant() {
+            rating::IDEAL
+        } else {
+            // The rate follows frequency scaling, only better than the PIT
+            rating::POOR + 10
+        }
+    }
+
+    fn frequency(&self) -> u64 {
+        self.frequency.load(Ordering::Relaxed)
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX
+    }
+
+    fn read(&self) -> u64 {
+        unsafe { _rdtsc() }
+    }
+}
+
+/// Frequency of the TSC in Hz, zero until the clock sources are registered.
+pub fn tsc_frequency() -> u64 {
+    TSC.frequency()
+}
+
+/// Register the clock sources of the platform.
+///
+/// Must run after the local APIC timer is calibrated, which also measures the
+/// TSC.
+pub fn module_init() {
+    TSC.frequency.store(Tsc::detect_frequency(), Ordering::Relaxed);
+    sys::time::clocksource::register(&TSC);
+
+    devices::hpet::module_init();
+
+    if let Some(pm_timer) = acpi::pm_timer::AcpiPmTimer::new() {
+        sys::time::clocksource::register(Box::leak(Box::new(pm_timer)));
+    }
+
+    pit::clocksource::init();
+    sys::time::clocksource::register(&pit::clocksource::PIT_CLOCKSOURCE);
+}
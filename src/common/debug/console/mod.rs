@@ -1,12 +1,13 @@
 use conquer_once::spin::OnceCell;
 use core::fmt;
-use spin::{Mutex, MutexGuard};
+use spin::MutexGuard;
 
 use crate::common::debug::graphics::canvas::{Color, SimpleCanvas};
 use crate::common::debug::graphics::screen::Screen;
 use crate::devices::efifb::SCREEN_INSTANCE;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
 
-pub static CONSOLE_INSTANCE: OnceCell<Mutex<Console<'static>>> = OnceCell::uninit();
+pub static CONSOLE_INSTANCE: OnceCell<IrqSpinLock<Console<'static>>> = OnceCell::uninit();
 
 const BACKUP_CHAR: char = '�';
 const CHAR_HEIGHT: usize = 16;
@@ -117,5 +118,5 @@ impl fmt::Write for Console<'_> {
 pub fn module_init() {
     let screen = SCREEN_INSTANCE.get().unwrap();
     let console = Console::new(screen.lock());
-    CONSOLE_INSTANCE.init_once(|| Mutex::new(console));
+    CONSOLE_INSTANCE.init_once(|| IrqSpinLock::new(console));
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
-use core::fmt::Write;
+use core::fmt::{self, Write};
 
 use crate::common::debug::console::CONSOLE_INSTANCE;
 use crate::devices::uart::u16550::SERIAL_WRITER;
+use crate::panic;
+use crate::sys;
+use crate::sys::time::wall::DateTime;
 use conquer_once::spin::OnceCell;
 use log::{Level, Metadata, Record};
 use spin::Mutex;
@@ -22,6 +25,23 @@ pub enum LogLevel {
 
 struct KernelLogger;
 
+/// A formatted log line, written the same way to every output without
+/// allocating.
+struct LogLine<'a> {
+    now: Option<DateTime>,
+    level: &'a str,
+    args: &'a fmt::Arguments<'a>,
+}
+
+impl fmt::Display for LogLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(now) = &self.now {
+            write!(f, "[{}] ", now)?;
+        }
+        write!(f, "[{}] {}", self.level, self.args)
+    }
+}
+
 impl log::Log for KernelLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         true
@@ -36,10 +56,14 @@ impl log::Log for KernelLogger {
             Level::Error => "\x1b[31mERROR\x1b[37m",
         };
 
-        let mut serial = SERIAL_WRITER.get().unwrap().lock();
-        let _ = writeln!(serial, "[{}] {}", level_str, record.args());
+        // Prefix the wall clock time once it is known. The clock source of a
+        // stopped CPU may never finish its update, so the panic report and
+        // an update in progress go without
+        let now = if panic::is_panicking() { None } else { sys::time::wall::try_now() };
+        let line = LogLine { now, level: level_str, args: record.args() };
+        let _ = writeln!(SERIAL_WRITER.get().unwrap().lock(), "{}", line);
         if let Some(console) = CONSOLE_INSTANCE.get() {
-            let _ = writeln!(console.lock(), "[{}] {}", level_str, record.args());
+            let _ = writeln!(console.lock(), "{}", line);
         }
     }
 
@@ -50,6 +74,21 @@ impl log::Log for KernelLogger {
     }
 }
 
+/// Release the locks of all log outputs.
+///
+/// # Safety
+///
+/// Only for the panic path, after every other CPU has been stopped, since a
+/// stopped CPU may have been holding an output lock.
+pub unsafe fn force_unlock() {
+    if let Some(serial) = SERIAL_WRITER.get() {
+        serial.force_unlock();
+    }
+    if let Some(console) = CONSOLE_INSTANCE.get() {
+        console.force_unlock();
+    }
+}
+
 pub fn module_init() {
     log::set_logger(&KernelLogger).unwrap();
     log::set_max_level(log::LevelFilter::Trace);
@@ -1,4 +1,7 @@
 use crate::abstracts::interrupt::controller::IrqHandler;
+use crate::common::structs::interrupt::stats::IrqStats;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::ops::Range;
 use id_alloc::IdAlloc;
 
@@ -8,6 +11,11 @@ pub enum IrqError {
     FailedToAllocIrqVector,
     HandlerAlreadyRegistered,
     HandlerNotRegistered,
+    InvalidCpu,
+    NotSupported,
+    NoController,
+    TooManyControllers,
+    VectorConflict,
 }
 
 pub type IrqResult<T = ()> = Result<T, IrqError>;
@@ -15,7 +23,9 @@ pub type IrqResult<T = ()> = Result<T, IrqError>;
 pub struct IrqManager<const IRQ_NUM: usize> {
     irq_idx_base: usize,
     handlers: [Option<IrqHandler>; IRQ_NUM],
+    owners: Vec<&'static str>,
     allocator: IdAlloc,
+    stats: IrqStats,
 }
 
 impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
@@ -24,11 +34,14 @@ impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
         Self {
             irq_idx_base: vec_range.start,
             handlers: [EMPTY; IRQ_NUM],
+            owners: vec![""; IRQ_NUM],
             allocator: IdAlloc::with_capacity(vec_range.len()),
+            stats: IrqStats::new(IRQ_NUM),
         }
     }
 
-    pub fn register_handler(&mut self, vector: usize, handler: IrqHandler) -> IrqResult<usize> {
+    /// Register `handler` owned by `name`, returns the vector it is bound to.
+    pub fn register_handler(&mut self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult<usize> {
         let idx = if vector == 0 {
             self.allocator.alloc().ok_or(IrqError::FailedToAllocIrqVector)?
         } else {
@@ -38,7 +51,8 @@ impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
                 .ok_or(IrqError::FailedToAllocIrqVector)?
         };
         self.handlers[idx] = Some(handler);
-        Ok(idx)
+        self.owners[idx] = name;
+        Ok(idx + self.irq_idx_base)
     }
 
     pub fn unregister_handler(&mut self, vector: usize) -> IrqResult {
@@ -50,11 +64,12 @@ impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
             return Err(IrqError::HandlerNotRegistered);
         }
         self.handlers[idx] = None;
+        self.owners[idx] = "";
         self.allocator.free(idx);
         Ok(())
     }
 
-    pub fn overwrite_handler(&mut self, vector: usize, handler: IrqHandler) -> IrqResult {
+    pub fn overwrite_handler(&mut self, vector: usize, name: &'static str, handler: IrqHandler) -> IrqResult {
         let idx = vector - self.irq_idx_base;
         if idx >= IRQ_NUM {
             return Err(IrqError::InvalidIrqVector);
@@ -63,6 +78,7 @@ impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
             return Err(IrqError::HandlerAlreadyRegistered);
         }
         self.handlers[idx] = Some(handler);
+        self.owners[idx] = name;
         Ok(())
     }
 
@@ -71,11 +87,32 @@ impl<const IRQ_NUM: usize> IrqManager<IRQ_NUM> {
         if idx >= IRQ_NUM {
             return Err(IrqError::InvalidIrqVector);
         }
+        self.stats.record(idx);
         if let Some(handler) = &self.handlers[idx] {
             handler(vector);
             Ok(())
         } else {
+            self.stats.record_unhandled();
             Err(IrqError::HandlerNotRegistered)
         }
     }
+
+    /// Vectors with a registered handler, along with their owners.
+    pub fn registered(&self) -> impl Iterator<Item=(usize, &'static str)> + '_ {
+        self.handlers
+            .iter()
+            .enumerate()
+            .filter(|(_, handler)| handler.is_some())
+            .map(|(idx, _)| (idx + self.irq_idx_base, self.owners[idx]))
+    }
+
+    /// Number of times `vector` fired on `cpu`.
+    pub fn count(&self, cpu: usize, vector: usize) -> u64 {
+        self.stats.count(cpu, vector - self.irq_idx_base)
+    }
+
+    /// Number of interrupts without a handler which fired on `cpu`.
+    pub fn unhandled(&self, cpu: usize) -> u64 {
+        self.stats.unhandled(cpu)
+    }
 }
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod manager;
\ No newline at end of file
+pub mod manager;
+pub mod stats;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use crate::abstracts::interrupt::controller::{IrqPolarity, IrqTriggerMode};
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Per-CPU counters of a range of interrupt vectors.
+pub struct IrqStats {
+    vector_num: usize,
+    counts: Vec<AtomicU64>,
+    unhandled: Vec<AtomicU64>,
+}
+
+impl IrqStats {
+    pub fn new(vector_num: usize) -> Self {
+        Self {
+            vector_num,
+            counts: (0..KERNEL_MAX_CPU_NUM * vector_num).map(|_| AtomicU64::new(0)).collect(),
+            unhandled: (0..KERNEL_MAX_CPU_NUM).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Count an interrupt of vector index `idx` on the current CPU.
+    pub fn record(&self, idx: usize) {
+        let cpu = sys::smp::current_id();
+        self.counts[cpu * self.vector_num + idx].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count an interrupt without handler on the current CPU.
+    pub fn record_unhandled(&self) {
+        self.unhandled[sys::smp::current_id()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self, cpu: usize, idx: usize) -> u64 {
+        self.counts[cpu * self.vector_num + idx].load(Ordering::Relaxed)
+    }
+
+    pub fn unhandled(&self, cpu: usize) -> u64 {
+        self.unhandled[cpu].load(Ordering::Relaxed)
+    }
+}
+
+/// Description of a registered interrupt.
+#[derive(Debug)]
+pub struct IrqInfo {
+    pub vector: usize,
+    /// Global system interrupt routed to the vector, if any.
+    pub gsi: Option<u32>,
+    pub trigger: Option<IrqTriggerMode>,
+    pub polarity: Option<IrqPolarity>,
+    pub owner: &'static str,
+    /// Number of times the vector fired, indexed by CPU.
+    pub counts: Vec<u64>,
+}
+
+/// Snapshot of all registered interrupts and their counters.
+#[derive(Debug, Default)]
+pub struct IrqInventory {
+    pub irqs: Vec<IrqInfo>,
+    /// Number of spurious interrupts, indexed by CPU.
+    pub spurious: Vec<u64>,
+    /// Number of interrupts without handler, indexed by CPU.
+    pub unhandled: Vec<u64>,
+}
+
+impl IrqInventory {
+    /// Append the interrupts of `other`, summing the per-CPU counters.
+    pub fn merge(&mut self, other: IrqInventory) {
+        self.irqs.extend(other.irqs);
+        Self::add_counts(&mut self.spurious, &other.spurious);
+        Self::add_counts(&mut self.unhandled, &other.unhandled);
+    }
+
+    fn add_counts(sum: &mut Vec<u64>, counts: &[u64]) {
+        if sum.len() < counts.len() {
+            sum.resize(counts.len(), 0);
+        }
+        for (sum, count) in sum.iter_mut().zip(counts) {
+            *sum += count;
+        }
+    }
+}
+
+impl fmt::Display for IrqInventory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6} {:>5} {:>8} {:>11}", "VECTOR", "GSI", "TRIGGER", "POLARITY")?;
+        for cpu in 0..self.spurious.len() {
+            write!(f, " {:>10}", format!("CPU{}", cpu))?;
+        }
+        writeln!(f, "  OWNER")?;
+
+        for irq in &self.irqs {
+            let gsi = irq.gsi.map_or(String::from("-"), |gsi| format!("{}", gsi));
+            let trigger = match irq.trigger {
+                Some(IrqTriggerMode::Edge) => "edge",
+                Some(IrqTriggerMode::Level) => "level",
+                None => "-",
+            };
+            let polarity = match irq.polarity {
+                Some(IrqPolarity::ActiveHigh) => "active-high",
+                Some(IrqPolarity::ActiveLow) => "active-low",
+                None => "-",
+            };
+            write!(f, "{:>#6x} {:>5} {:>8} {:>11}", irq.vector, gsi, trigger, polarity)?;
+            for count in &irq.counts {
+                write!(f, " {:>10}", count)?;
+            }
+            writeln!(f, "  {}", irq.owner)?;
+        }
+
+        write!(f, "{:>33}", "SPURIOUS")?;
+        for count in &self.spurious {
+            write!(f, " {:>10}", count)?;
+        }
+        writeln!(f)?;
+        write!(f, "{:>33}", "UNHANDLED")?;
+        for count in &self.unhandled {
+            write!(f, " {:>10}", count)?;
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
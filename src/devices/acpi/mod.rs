@@ -1,8 +1,13 @@
+use crate::abstracts::interrupt::controller::{IrqPolarity, IrqTriggerMode};
 use crate::boot::BOOTINFO;
 use crate::info;
-use acpi::{AcpiHandler, AcpiTables, PhysicalMapping};
+use acpi::fadt::Fadt;
+use acpi::platform::interrupt::{Polarity, TriggerMode};
+use acpi::{AcpiHandler, AcpiTables, InterruptModel, PhysicalMapping};
 use alloc::sync::Arc;
 
+pub mod pm_timer;
+
 static mut ACPI_TABLES: Option<Arc<AcpiTables<AcpiHandlerImpl>>> = None;
 
 pub fn module_init() {
@@ -22,6 +27,33 @@ pub fn get_acpi_tables() -> Arc<AcpiTables<AcpiHandlerImpl>> {
     unsafe { Arc::clone(ACPI_TABLES.as_ref().unwrap()) }
 }
 
+/// GSI, trigger mode and polarity of the legacy ISA interrupt `irq`, after
+/// the interrupt source overrides of the MADT.
+pub fn isa_irq_route(irq: u8) -> (u32, IrqTriggerMode, IrqPolarity) {
+    let mut route = (irq as u32, IrqTriggerMode::Edge, IrqPolarity::ActiveHigh);
+    if let Ok(InterruptModel::Apic(apic)) = get_acpi_tables().platform_info().map(|info| info.interrupt_model) {
+        if let Some(over) = apic.interrupt_source_overrides.iter().find(|over| over.isa_source == irq) {
+            route.0 = over.global_system_interrupt;
+            if over.trigger_mode == TriggerMode::Level {
+                route.1 = IrqTriggerMode::Level;
+            }
+            if over.polarity == Polarity::ActiveLow {
+                route.2 = IrqPolarity::ActiveLow;
+            }
+        }
+    }
+    route
+}
+
+/// Index of the CMOS century register reported by the FADT, if any.
+pub fn century_register() -> Option<u8> {
+    let fadt = get_acpi_tables().find_table::<Fadt>().ok()?;
+    match fadt.century {
+        0 => None,
+        century => Some(century),
+    }
+}
+
 #[derive(Clone)]
 pub struct AcpiHandlerImpl;
 
@@ -0,0 +1,67 @@
+use crate::abstracts::memory::address::AddressSpaceHAL;
+use crate::abstracts::time::clocksource::{rating, ClockSource};
+use crate::devices::acpi::get_acpi_tables;
+use crate::sys;
+use acpi::address::AddressSpace;
+use log::warn;
+use x86_64::instructions::port::Port;
+
+/// Frequency of the ACPI power management timer in Hz.
+pub const PM_TIMER_FREQUENCY: u64 = 3_579_545;
+
+enum PmTimerRegister {
+    Io(u16),
+    Memory(usize),
+}
+
+/// The ACPI power management timer described by the FADT.
+pub struct AcpiPmTimer {
+    register: PmTimerRegister,
+    /// The counter is either 24 or 32 bits wide.
+    mask: u64,
+}
+
+impl AcpiPmTimer {
+    /// Locate the timer from the FADT, `None` if the platform has none.
+    pub fn new() -> Option<Self> {
+        let pm_timer = get_acpi_tables().platform_info().ok()?.pm_timer?;
+        let register = match pm_timer.base.address_space {
+            AddressSpace::SystemIo => PmTimerRegister::Io(pm_timer.base.address as u16),
+            AddressSpace::SystemMemory => {
+                PmTimerRegister::Memory(sys::mem::address_space::phys_to_virt(pm_timer.base.address as usize))
+            }
+            other => {
+                warn!("ACPI PM timer in unsupported address space {:?}", other);
+                return None;
+            }
+        };
+        let mask = if pm_timer.supports_32bit { u32::MAX as u64 } else { 0xff_ffff };
+        Some(Self { register, mask })
+    }
+}
+
+impl ClockSource for AcpiPmTimer {
+    fn name(&self) -> &'static str {
+        "acpi_pm"
+    }
+
+    fn rating(&self) -> u32 {
+        rating::FAIR
+    }
+
+    fn frequency(&self) -> u64 {
+        PM_TIMER_FREQUENCY
+    }
+
+    fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    fn read(&self) -> u64 {
+        let value = match self.register {
+            PmTimerRegister::Io(port) => unsafe { Port::<u32>::new(port).read() },
+            PmTimerRegister::Memory(vaddr) => unsafe { core::ptr::read_volatile(vaddr as *const u32) },
+        };
+        value as u64 & self.mask
+    }
+}
\ No newline at end of file
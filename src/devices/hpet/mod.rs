@@ -0,0 +1,359 @@
+use crate::abstracts::interrupt::controller::{IrqHandler, IrqPolarity, IrqTriggerMode};
+use crate::abstracts::memory::address::AddressSpaceHAL;
+use crate::abstracts::time::clockevent::{ClockEventDevice, ClockEventError, ClockEventFeatures, ClockEventMode, ClockEventResult};
+use crate::abstracts::time::clocksource::{rating, ClockSource};
+use crate::devices::acpi::get_acpi_tables;
+use crate::devices::{DeviceError, DeviceResult};
+use crate::sys;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use acpi::HpetInfo;
+use alloc::vec::Vec;
+use conquer_once::spin::OnceCell;
+use core::sync::atomic::{AtomicU8, Ordering};
+use log::{error, info};
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+const FSEC_PER_SEC: u64 = 1_000_000_000_000_000;
+/// Longest counter period allowed by the specification, 100 ns.
+const HPET_MAX_PERIOD_FS: u64 = 0x05f5_e100;
+
+const HPET_CAPABILITIES: usize = 0x000;
+const HPET_CONFIG: usize = 0x010;
+const HPET_MAIN_COUNTER: usize = 0x0f0;
+const HPET_TIMER_CONFIG: usize = 0x100;
+const HPET_TIMER_COMPARATOR: usize = 0x108;
+const HPET_TIMER_FSB_ROUTE: usize = 0x110;
+const HPET_TIMER_STRIDE: usize = 0x20;
+
+const HPET_CAP_COUNT_SIZE: u64 = 1 << 13;
+const HPET_CONFIG_ENABLE: u64 = 1 << 0;
+
+const TIMER_LEVEL_TRIGGERED: u64 = 1 << 1;
+const TIMER_INT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAP: u64 = 1 << 4;
+const TIMER_SIZE_CAP: u64 = 1 << 5;
+const TIMER_VAL_SET: u64 = 1 << 6;
+const TIMER_32BIT_MODE: u64 = 1 << 8;
+const TIMER_INT_ROUTE_SHIFT: u64 = 9;
+const TIMER_INT_ROUTE_MASK: u64 = 0x1f << TIMER_INT_ROUTE_SHIFT;
+const TIMER_FSB_ENABLE: u64 = 1 << 14;
+const TIMER_FSB_CAP: u64 = 1 << 15;
+const TIMER_INT_ROUTE_CAP_SHIFT: u64 = 32;
+
+/// Smallest delta accepted by a comparator, writes closer to the counter may
+/// be missed.
+const MIN_DELTA_TICKS: u64 = 1024;
+
+static HPET: OnceCell<Hpet> = OnceCell::uninit();
+
+/// The High Precision Event Timer described by the ACPI HPET table.
+pub struct Hpet {
+    base_vaddr: usize,
+    /// Frequency of the main counter in Hz.
+    frequency: u64,
+    counter_64bit: bool,
+    comparators: Vec<HpetComparator>,
+    /// Serializes read-modify-write of the configuration registers.
+    lock: IrqSpinLock<()>,
+}
+
+/// Delivery of the interrupt of a comparator.
+#[derive(Debug, Clone, Copy)]
+pub enum HpetRoute {
+    /// Through the I/O APIC input `gsi`, which must be listed in the routing
+    /// capability of the comparator.
+    IoApic(u32),
+    /// As a message signalled interrupt written on the front side bus,
+    /// delivered to the CPU `cpu`.
+    Fsb(usize),
+}
+
+/// A comparator of the HPET, raising an interrupt when the main counter
+/// reaches its value.
+pub struct HpetComparator {
+    index: usize,
+    mode: AtomicU8,
+}
+
+impl Hpet {
+    /// Reset the HPET described by `info`, fails if it reports an invalid
+    /// counter period.
+    fn new(info: &HpetInfo) -> DeviceResult<Self> {
+        let base_vaddr = sys::mem::address_space::phys_to_virt(info.base_address);
+        let mut hpet = Self {
+            base_vaddr,
+            frequency: 0,
+            counter_64bit: false,
+            comparators: Vec::new(),
+            lock: IrqSpinLock::new(()),
+        };
+
+        let capabilities = hpet.read(HPET_CAPABILITIES);
+        let period = capabilities >> 32;
+        if period == 0 || period > HPET_MAX_PERIOD_FS {
+            error!("HPET at {:#x} reports an invalid period of {} fs, skipped", info.base_address, period);
+            return Err(DeviceError::NotSupported);
+        }
+        hpet.frequency = FSEC_PER_SEC / period;
+        hpet.counter_64bit = capabilities & HPET_CAP_COUNT_SIZE != 0;
+        hpet.comparators = (0..info.num_comparators() as usize)
+            .map(|index| HpetComparator {
+                index,
+                mode: AtomicU8::new(ClockEventMode::Shutdown as u8),
+            })
+            .collect();
+
+        // Start from a known state: all comparators off, then the counter on
+        hpet.write(HPET_CONFIG, hpet.read(HPET_CONFIG) & !HPET_CONFIG_ENABLE);
+        for comparator in &hpet.comparators {
+            let config = hpet.timer_read(comparator.index, HPET_TIMER_CONFIG);
+            hpet.timer_write(comparator.index, HPET_TIMER_CONFIG, config & !(TIMER_INT_ENABLE | TIMER_PERIODIC | TIMER_FSB_ENABLE));
+        }
+        hpet.write(HPET_MAIN_COUNTER, 0);
+        hpet.write(HPET_CONFIG, hpet.read(HPET_CONFIG) | HPET_CONFIG_ENABLE);
+        Ok(hpet)
+    }
+
+    fn read(&self, offset: usize) -> u64 {
+        unsafe { core::ptr::read_volatile((self.base_vaddr + offset) as *const u64) }
+    }
+
+    fn write(&self, offset: usize, value: u64) {
+        unsafe { core::ptr::write_volatile((self.base_vaddr + offset) as *mut u64, value) }
+    }
+
+    fn timer_read(&self, index: usize, offset: usize) -> u64 {
+        self.read(offset + index * HPET_TIMER_STRIDE)
+    }
+
+    fn timer_write(&self, index: usize, offset: usize, value: u64) {
+        self.write(offset + index * HPET_TIMER_STRIDE, value)
+    }
+
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.read(HPET_MAIN_COUNTER)
+    }
+
+    pub fn comparators(&self) -> &[HpetComparator] {
+        &self.comparators
+    }
+
+    fn ns_to_ticks(&self, ns: u64) -> u64 {
+        (ns as u128 * self.frequency as u128 / NSEC_PER_SEC as u128) as u64
+    }
+
+    fn ticks_to_ns(&self, ticks: u64) -> u64 {
+        (ticks as u128 * NSEC_PER_SEC as u128 / self.frequency as u128) as u64
+    }
+}
+
+impl ClockSource for Hpet {
+    fn name(&self) -> &'static str {
+        "hpet"
+    }
+
+    fn rating(&self) -> u32 {
+        // Global and stable, but each read is an uncached MMIO access
+        rating::GOOD - 50
+    }
+
+    fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    fn mask(&self) -> u64 {
+        if self.counter_64bit { u64::MAX } else { u32::MAX as u64 }
+    }
+
+    fn read(&self) -> u64 {
+        self.counter() & self.mask()
+    }
+}
+
+impl HpetComparator {
+    fn hpet(&self) -> &'static Hpet {
+        get()
+    }
+
+    fn config(&self) -> u64 {
+        self.hpet().timer_read(self.index, HPET_TIMER_CONFIG)
+    }
+
+    fn set_config(&self, config: u64) {
+        self.hpet().timer_write(self.index, HPET_TIMER_CONFIG, config)
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// I/O APIC inputs the comparator can be routed to.
+    pub fn ioapic_routes(&self) -> impl Iterator<Item=u32> {
+        let cap = (self.config() >> TIMER_INT_ROUTE_CAP_SHIFT) as u32;
+        (0..32).filter(move |gsi| cap & (1 << gsi) != 0)
+    }
+
+    pub fn supports_fsb(&self) -> bool {
+        self.config() & TIMER_FSB_CAP != 0
+    }
+
+    pub fn supports_periodic(&self) -> bool {
+        self.config() & TIMER_PERIODIC_CAP != 0
+    }
+
+    fn is_64bit(&self) -> bool {
+        self.config() & TIMER_SIZE_CAP != 0
+    }
+
+    /// Deliver the interrupt of the comparator to `handler` through `route`.
+    pub fn route(&self, route: HpetRoute, name: &'static str, handler: IrqHandler) -> DeviceResult {
+        let ic = sys::interrupt::get_ic();
+        let _guard = self.hpet().lock.lock();
+        let mut config = self.config() & !(TIMER_INT_ROUTE_MASK | TIMER_FSB_ENABLE | TIMER_LEVEL_TRIGGERED);
+        match route {
+            HpetRoute::IoApic(gsi) => {
+                if !self.ioapic_routes().any(|route| route == gsi) {
+                    return Err(DeviceError::InvalidParam);
+                }
+                ic.configure(gsi as usize, IrqTriggerMode::Edge, IrqPolarity::ActiveHigh)
+                    .map_err(|_| DeviceError::InvalidParam)?;
+                ic.register_irq_handler(gsi as usize, name, handler)
+                    .map_err(|_| DeviceError::AlreadyExists)?;
+                if ic.unmask_irq(gsi as usize).is_err() {
+                    // Leave the line free for a later attempt
+                    let _ = ic.unregister_irq_handler(gsi as usize);
+                    return Err(DeviceError::InvalidParam);
+                }
+                config |= (gsi as u64) << TIMER_INT_ROUTE_SHIFT;
+            }
+            HpetRoute::Fsb(cpu) => {
+                if !self.supports_fsb() {
+                    return Err(DeviceError::NotSupported);
+                }
+                let msg = ic.register_msi_handler(cpu, name, handler)
+                    .map_err(|_| DeviceError::NoResources)?;
+                self.hpet().timer_write(self.index, HPET_TIMER_FSB_ROUTE, msg.address << 32 | msg.data as u64);
+                config |= TIMER_FSB_ENABLE;
+            }
+        }
+        if !self.is_64bit() || !self.hpet().counter_64bit {
+            config |= TIMER_32BIT_MODE;
+        }
+        self.set_config(config);
+        Ok(())
+    }
+
+    fn set_mode(&self, mode: ClockEventMode) {
+        self.mode.store(mode as u8, Ordering::Relaxed);
+    }
+
+    fn counter_mask(&self) -> u64 {
+        if self.config() & TIMER_32BIT_MODE != 0 { u32::MAX as u64 } else { u64::MAX }
+    }
+}
+
+impl ClockEventDevice for HpetComparator {
+    fn name(&self) -> &'static str {
+        "hpet"
+    }
+
+    fn features(&self) -> ClockEventFeatures {
+        if self.supports_periodic() {
+            ClockEventFeatures::ONESHOT | ClockEventFeatures::PERIODIC
+        } else {
+            ClockEventFeatures::ONESHOT
+        }
+    }
+
+    fn min_delta_ns(&self) -> u64 {
+        self.hpet().ticks_to_ns(MIN_DELTA_TICKS)
+    }
+
+    fn max_delta_ns(&self) -> u64 {
+        self.hpet().ticks_to_ns(self.counter_mask() >> 1)
+    }
+
+    fn mode(&self) -> ClockEventMode {
+        match self.mode.load(Ordering::Relaxed) {
+            mode if mode == ClockEventMode::Periodic as u8 => ClockEventMode::Periodic,
+            mode if mode == ClockEventMode::OneShot as u8 => ClockEventMode::OneShot,
+            _ => ClockEventMode::Shutdown,
+        }
+    }
+
+    fn shutdown(&self) -> ClockEventResult {
+        let _guard = self.hpet().lock.lock();
+        self.set_config(self.config() & !(TIMER_INT_ENABLE | TIMER_PERIODIC));
+        self.set_mode(ClockEventMode::Shutdown);
+        Ok(())
+    }
+
+    fn set_periodic(&self, period_ns: u64) -> ClockEventResult {
+        if !self.supports_periodic() {
+            return Err(ClockEventError::NotSupported);
+        }
+        let hpet = self.hpet();
+        let period = hpet.ns_to_ticks(period_ns).max(MIN_DELTA_TICKS);
+        let _guard = hpet.lock.lock();
+        self.set_config(self.config() | TIMER_INT_ENABLE | TIMER_PERIODIC | TIMER_VAL_SET);
+        // With VAL_SET, the first write sets the comparator and the second
+        // one the period
+        hpet.timer_write(self.index, HPET_TIMER_COMPARATOR, (hpet.counter() + period) & self.counter_mask());
+        hpet.timer_write(self.index, HPET_TIMER_COMPARATOR, period);
+        self.set_mode(ClockEventMode::Periodic);
+        Ok(())
+    }
+
+    fn set_oneshot(&self) -> ClockEventResult {
+        let _guard = self.hpet().lock.lock();
+        self.set_config((self.config() | TIMER_INT_ENABLE) & !TIMER_PERIODIC);
+        self.set_mode(ClockEventMode::OneShot);
+        Ok(())
+    }
+
+    fn program_event(&self, delta_ns: u64) -> ClockEventResult {
+        if self.mode() != ClockEventMode::OneShot {
+            return Err(ClockEventError::InvalidMode);
+        }
+        let hpet = self.hpet();
+        let delta = hpet.ns_to_ticks(delta_ns).clamp(MIN_DELTA_TICKS, self.counter_mask() >> 1);
+        hpet.timer_write(self.index, HPET_TIMER_COMPARATOR, (hpet.counter() + delta) & self.counter_mask());
+        Ok(())
+    }
+}
+
+/// The HPET, if the platform has one.
+pub fn try_get() -> Option<&'static Hpet> {
+    HPET.get()
+}
+
+pub fn get() -> &'static Hpet {
+    HPET.get().expect("HPET not initialized")
+}
+
+/// Probe the HPET from the ACPI tables and register its main counter as a
+/// clock source.
+pub fn module_init() {
+    let tables = get_acpi_tables();
+    let Ok(info) = HpetInfo::new(&*tables) else {
+        return;
+    };
+    info!("☞ Hikari HPET Module");
+    let Ok(hpet) = Hpet::new(&info) else {
+        return;
+    };
+    let hpet = HPET.get_or_init(|| hpet);
+    info!(
+        "HPET at {:#x}: {} Hz, {} comparators, {}-bit counter",
+        info.base_address,
+        hpet.frequency,
+        hpet.comparators.len(),
+        if hpet.counter_64bit { 64 } else { 32 }
+    );
+    sys::time::clocksource::register(hpet);
+}
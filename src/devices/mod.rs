@@ -1,6 +1,9 @@
 pub mod uart;
 pub mod acpi;
 pub mod efifb;
+pub mod hpet;
+pub mod pit;
+pub mod rtc;
 
 /// The error type for external device.
 #[derive(Debug)]
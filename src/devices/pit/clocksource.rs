@@ -0,0 +1,63 @@
+use crate::abstracts::time::clocksource::{rating, ClockSource};
+use crate::devices::pit::{PIT_COMMAND_PORT, PIT_FREQUENCY};
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use x86_64::instructions::port::Port;
+
+const PIT_CHANNEL0_DATA_PORT: u16 = 0x40;
+
+pub static PIT_CLOCKSOURCE: PitClockSource = PitClockSource { lock: IrqSpinLock::new(()) };
+
+/// Channel 0 of the PIT as a free running 16-bit counter.
+///
+/// It wraps around every 55ms, so it only keeps time while the tick runs.
+pub struct PitClockSource {
+    /// Latching and reading the count takes three port accesses.
+    lock: IrqSpinLock<()>,
+}
+
+/// Start channel 0 counting down from 65536 repeatedly.
+pub fn init() {
+    let _guard = PIT_CLOCKSOURCE.lock.lock();
+    let mut command: Port<u8> = Port::new(PIT_COMMAND_PORT);
+    let mut data: Port<u8> = Port::new(PIT_CHANNEL0_DATA_PORT);
+    unsafe {
+        // Channel 0, lobyte/hibyte access, mode 2 (rate generator), binary
+        command.write(0b0011_0100);
+        // A reload value of 0 counts 65536 cycles
+        data.write(0);
+        data.write(0);
+    }
+}
+
+impl ClockSource for PitClockSource {
+    fn name(&self) -> &'static str {
+        "pit"
+    }
+
+    fn rating(&self) -> u32 {
+        rating::POOR
+    }
+
+    fn frequency(&self) -> u64 {
+        PIT_FREQUENCY
+    }
+
+    fn mask(&self) -> u64 {
+        u16::MAX as u64
+    }
+
+    fn read(&self) -> u64 {
+        let _guard = self.lock.lock();
+        let mut command: Port<u8> = Port::new(PIT_COMMAND_PORT);
+        let mut data: Port<u8> = Port::new(PIT_CHANNEL0_DATA_PORT);
+        let count = unsafe {
+            // Latch the count of channel 0
+            command.write(0b0000_0000);
+            let low = data.read() as u16;
+            let high = data.read() as u16;
+            high << 8 | low
+        };
+        // The channel counts down
+        0u16.wrapping_sub(count) as u64
+    }
+}
@@ -0,0 +1,43 @@
+use x86_64::instructions::port::Port;
+
+pub mod clocksource;
+
+/// Frequency of the PIT input clock in Hz.
+pub const PIT_FREQUENCY: u64 = 1_193_182;
+
+const PIT_CHANNEL2_DATA_PORT: u16 = 0x42;
+const PIT_COMMAND_PORT: u16 = 0x43;
+const PIT_CHANNEL2_GATE_PORT: u16 = 0x61;
+
+const GATE_ENABLE: u8 = 1 << 0;
+const SPEAKER_ENABLE: u8 = 1 << 1;
+const CHANNEL2_OUTPUT: u8 = 1 << 5;
+
+/// Busy wait `us` microseconds with channel 2 of the PIT.
+///
+/// The output of channel 2 can be polled through port 0x61, so this works
+/// before any interrupt is available. `us` must not exceed 54925.
+pub fn busy_wait_us(us: u64) {
+    let count = PIT_FREQUENCY * us / 1_000_000;
+    assert!(count > 0 && count <= u16::MAX as u64, "PIT can not wait for {} us", us);
+
+    let mut gate: Port<u8> = Port::new(PIT_CHANNEL2_GATE_PORT);
+    let mut command: Port<u8> = Port::new(PIT_COMMAND_PORT);
+    let mut data: Port<u8> = Port::new(PIT_CHANNEL2_DATA_PORT);
+    unsafe {
+        let value = gate.read() & !(SPEAKER_ENABLE | GATE_ENABLE);
+        gate.write(value);
+
+        // Channel 2, lobyte/hibyte access, mode 0 (interrupt on terminal count), binary
+        command.write(0b1011_0000);
+        data.write(count as u8);
+        data.write((count >> 8) as u8);
+
+        // Counting starts on the rising edge of the gate
+        gate.write(value | GATE_ENABLE);
+        while gate.read() & CHANNEL2_OUTPUT == 0 {
+            core::hint::spin_loop();
+        }
+        gate.write(value);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,243 @@
+use crate::devices::acpi;
+use crate::devices::{DeviceError, DeviceResult};
+use crate::sys;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::time::wall::DateTime;
+use crate::sys::time::NSEC_PER_SEC;
+use alloc::boxed::Box;
+use conquer_once::spin::OnceCell;
+use log::{error, info};
+use x86_64::instructions::port::Port;
+
+const CMOS_INDEX_PORT: u16 = 0x70;
+const CMOS_DATA_PORT: u16 = 0x71;
+const RTC_ISA_IRQ: u8 = 8;
+
+const RTC_SECONDS: u8 = 0x00;
+const RTC_SECONDS_ALARM: u8 = 0x01;
+const RTC_MINUTES: u8 = 0x02;
+const RTC_MINUTES_ALARM: u8 = 0x03;
+const RTC_HOURS: u8 = 0x04;
+const RTC_HOURS_ALARM: u8 = 0x05;
+const RTC_DAY_OF_MONTH: u8 = 0x07;
+const RTC_MONTH: u8 = 0x08;
+const RTC_YEAR: u8 = 0x09;
+const RTC_STATUS_A: u8 = 0x0a;
+const RTC_STATUS_B: u8 = 0x0b;
+const RTC_STATUS_C: u8 = 0x0c;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_A_RATE_MASK: u8 = 0x0f;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const STATUS_B_ALARM_INT: u8 = 1 << 5;
+const STATUS_B_PERIODIC_INT: u8 = 1 << 6;
+const STATUS_C_ALARM: u8 = 1 << 5;
+const STATUS_C_PERIODIC: u8 = 1 << 6;
+const HOUR_PM: u8 = 1 << 7;
+
+/// Valid rates of the periodic interrupt, firing at `32768 >> (rate - 1)` Hz.
+pub const RTC_PERIODIC_RATES: core::ops::RangeInclusive<u8> = 3..=15;
+
+/// Callback invoked from the RTC interrupt.
+pub type RtcHandler = fn();
+
+/// Index of the century register, from the ACPI FADT.
+static CENTURY_REGISTER: OnceCell<Option<u8>> = OnceCell::uninit();
+/// Serializes accesses through the CMOS index port.
+static CMOS_LOCK: IrqSpinLock<()> = IrqSpinLock::new(());
+/// Read by the RTC interrupt, so written with it masked.
+static PERIODIC_HANDLER: IrqSpinLock<Option<RtcHandler>> = IrqSpinLock::new(None);
+static ALARM_HANDLER: IrqSpinLock<Option<RtcHandler>> = IrqSpinLock::new(None);
+
+fn cmos_read(reg: u8) -> u8 {
+    unsafe {
+        Port::<u8>::new(CMOS_INDEX_PORT).write(reg);
+        Port::<u8>::new(CMOS_DATA_PORT).read()
+    }
+}
+
+fn cmos_write(reg: u8, value: u8) {
+    unsafe {
+        Port::<u8>::new(CMOS_INDEX_PORT).write(reg);
+        Port::<u8>::new(CMOS_DATA_PORT).write(value);
+    }
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0f)
+}
+
+fn binary_to_bcd(value: u8) -> u8 {
+    (value / 10) << 4 | value % 10
+}
+
+/// Raw time registers, in the format selected by status register B.
+#[derive(PartialEq, Eq)]
+struct RawTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+impl RawTime {
+    fn read() -> Self {
+        while cmos_read(RTC_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+            core::hint::spin_loop();
+        }
+        Self {
+            second: cmos_read(RTC_SECONDS),
+            minute: cmos_read(RTC_MINUTES),
+            hour: cmos_read(RTC_HOURS),
+            day: cmos_read(RTC_DAY_OF_MONTH),
+            month: cmos_read(RTC_MONTH),
+            year: cmos_read(RTC_YEAR),
+            century: CENTURY_REGISTER.get().copied().flatten().map_or(0, cmos_read),
+        }
+    }
+
+    fn to_datetime(&self, status_b: u8) -> DateTime {
+        let decode = |value: u8| if status_b & STATUS_B_BINARY != 0 { value } else { bcd_to_binary(value) };
+        let mut hour = decode(self.hour & !HOUR_PM);
+        if status_b & STATUS_B_24_HOUR == 0 {
+            // 12 AM is midnight and 12 PM is noon
+            hour %= 12;
+            if self.hour & HOUR_PM != 0 {
+                hour += 12;
+            }
+        }
+        let century = match CENTURY_REGISTER.get().copied().flatten() {
+            Some(_) => decode(self.century) as u16,
+            None => 20,
+        };
+        DateTime {
+            year: century * 100 + decode(self.year) as u16,
+            month: decode(self.month),
+            day: decode(self.day),
+            hour,
+            minute: decode(self.minute),
+            second: decode(self.second),
+        }
+    }
+}
+
+/// Encode `value` of the time register `reg` in the format of status
+/// register B.
+fn encode(reg: u8, value: u8, status_b: u8) -> u8 {
+    let (value, pm) = if reg == RTC_HOURS_ALARM && status_b & STATUS_B_24_HOUR == 0 {
+        (if value % 12 == 0 { 12 } else { value % 12 }, value >= 12)
+    } else {
+        (value, false)
+    };
+    let value = if status_b & STATUS_B_BINARY != 0 { value } else { binary_to_bcd(value) };
+    if pm { value | HOUR_PM } else { value }
+}
+
+/// Read the current date and time.
+///
+/// The registers are read until two consecutive reads agree, so an update
+/// happening in between is never seen half-way.
+pub fn read_time() -> DateTime {
+    let _guard = CMOS_LOCK.lock();
+    let mut time = RawTime::read();
+    loop {
+        let again = RawTime::read();
+        if again == time {
+            break;
+        }
+        time = again;
+    }
+    time.to_datetime(cmos_read(RTC_STATUS_B))
+}
+
+/// Raise the periodic interrupt at `32768 >> (rate - 1)` Hz and call
+/// `handler` on each one.
+pub fn set_periodic(rate: u8, handler: RtcHandler) -> DeviceResult {
+    if !RTC_PERIODIC_RATES.contains(&rate) {
+        return Err(DeviceError::InvalidParam);
+    }
+    *PERIODIC_HANDLER.lock() = Some(handler);
+    let _guard = CMOS_LOCK.lock();
+    let status_a = cmos_read(RTC_STATUS_A);
+    cmos_write(RTC_STATUS_A, status_a & !STATUS_A_RATE_MASK | rate);
+    cmos_write(RTC_STATUS_B, cmos_read(RTC_STATUS_B) | STATUS_B_PERIODIC_INT);
+    Ok(())
+}
+
+pub fn disable_periodic() {
+    let _guard = CMOS_LOCK.lock();
+    cmos_write(RTC_STATUS_B, cmos_read(RTC_STATUS_B) & !STATUS_B_PERIODIC_INT);
+    *PERIODIC_HANDLER.lock() = None;
+}
+
+/// Call `handler` every day once the RTC reaches `hour:minute:second`.
+pub fn set_alarm(hour: u8, minute: u8, second: u8, handler: RtcHandler) -> DeviceResult {
+    if hour >= 24 || minute >= 60 || second >= 60 {
+        return Err(DeviceError::InvalidParam);
+    }
+    *ALARM_HANDLER.lock() = Some(handler);
+    let _guard = CMOS_LOCK.lock();
+    let status_b = cmos_read(RTC_STATUS_B);
+    cmos_write(RTC_SECONDS_ALARM, encode(RTC_SECONDS_ALARM, second, status_b));
+    cmos_write(RTC_MINUTES_ALARM, encode(RTC_MINUTES_ALARM, minute, status_b));
+    cmos_write(RTC_HOURS_ALARM, encode(RTC_HOURS_ALARM, hour, status_b));
+    cmos_write(RTC_STATUS_B, status_b | STATUS_B_ALARM_INT);
+    Ok(())
+}
+
+pub fn clear_alarm() {
+    let _guard = CMOS_LOCK.lock();
+    cmos_write(RTC_STATUS_B, cmos_read(RTC_STATUS_B) & !STATUS_B_ALARM_INT);
+    *ALARM_HANDLER.lock() = None;
+}
+
+fn handle_irq() {
+    // Reading status register C acknowledges the interrupt, the RTC raises no
+    // further interrupt until then
+    let status_c = {
+        let _guard = CMOS_LOCK.lock();
+        cmos_read(RTC_STATUS_C)
+    };
+    if status_c & STATUS_C_PERIODIC != 0 {
+        let handler = *PERIODIC_HANDLER.lock();
+        if let Some(handler) = handler {
+            handler();
+        }
+    }
+    if status_c & STATUS_C_ALARM != 0 {
+        let handler = *ALARM_HANDLER.lock();
+        if let Some(handler) = handler {
+            handler();
+        }
+    }
+}
+
+/// Read the RTC, set the wall clock from it and route its interrupt.
+///
+/// Must run after a clock source is registered.
+pub fn module_init() {
+    info!("☞ Hikari RTC Module");
+    CENTURY_REGISTER.init_once(acpi::century_register);
+
+    let time = read_time();
+    info!("RTC time: {} UTC", time);
+    sys::time::wall::set_realtime_ns(time.to_unix_secs() * NSEC_PER_SEC);
+
+    // Drop any interrupt latched before the handler exists
+    {
+        let _guard = CMOS_LOCK.lock();
+        cmos_read(RTC_STATUS_C);
+    }
+    let (gsi, tm, pol) = acpi::isa_irq_route(RTC_ISA_IRQ);
+    let ic = sys::interrupt::get_ic();
+    let result = ic.configure(gsi as usize, tm, pol)
+        .and_then(|_| ic.register_irq_handler(gsi as usize, "rtc", Box::new(|_| handle_irq())))
+        .and_then(|_| ic.unmask_irq(gsi as usize));
+    if let Err(err) = result {
+        error!("Failed to route RTC interrupt to GSI {}: {:?}", gsi, err);
+    }
+}
@@ -1,9 +1,9 @@
 use core::fmt;
 
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
 use conquer_once::spin::OnceCell;
-use spin::Mutex;
 
-pub static SERIAL_WRITER: OnceCell<Mutex<SerialPort>> = OnceCell::uninit();
+pub static SERIAL_WRITER: OnceCell<IrqSpinLock<SerialPort>> = OnceCell::uninit();
 
 pub struct SerialPort {
     port: uart_16550::SerialPort,
@@ -29,6 +29,6 @@ impl fmt::Write for SerialPort {
 
 pub fn module_init() {
     SERIAL_WRITER.init_once(move || {
-        Mutex::new(unsafe { SerialPort::init() })
+        IrqSpinLock::new(unsafe { SerialPort::init() })
     });
 }
\ No newline at end of file
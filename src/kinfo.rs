@@ -8,6 +8,8 @@ pub const KERNEL_PATCH_VERSION: u8 = 0;
 pub const KERNEL_LOCAL_VERSION: &str = "alpha";
 pub const KERNEL_STACK_SIZE: usize = 1 << 12; // 4 MiB
 pub const KERNEL_STACK_TRACE_FRAME_NUM: usize = 16;
+pub const KERNEL_MAX_CPU_NUM: usize = 64;
+pub const KERNEL_TICK_HZ: u64 = 100;
 
 pub fn print_sys_info() {
     info!(
@@ -20,6 +22,8 @@ pub fn print_sys_info() {
     );
     info!("Stack size: {} bytes", KERNEL_STACK_SIZE);
     info!("Stack trace frame number: {}", KERNEL_STACK_TRACE_FRAME_NUM);
+    info!("Max CPU number: {}", KERNEL_MAX_CPU_NUM);
+    info!("Tick frequency: {} Hz", KERNEL_TICK_HZ);
 
     // Print the system information
     let cpuid = CpuId::new();
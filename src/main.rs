@@ -10,7 +10,6 @@ use common::structs::interrupt;
 use core::arch::asm;
 use core::fmt::Write;
 use log::{debug, info};
-use raw_cpuid::CpuId;
 use sys::mem::paging::PageTable;
 
 mod arch;
@@ -24,22 +23,23 @@ mod abstracts;
 
 unsafe fn kmain() -> ! {
     let interrupt_controller = sys::interrupt::get_ic();
-    interrupt_controller.as_ref().register_irq_handler(0, Box::new(|_| {
+    interrupt_controller.register_irq_handler(0, "test", Box::new(|_| {
         test();
     })).unwrap();
     asm!("int 32");
+    sys::interrupt::dump_inventory();
     panic!("内核功能尚未完备，暂时无法继续运行。");
 }
 
 unsafe fn secondary_main() -> ! {
     // TODO: Wait for kernel exit
     asm!("int 32");
-    loop {
-        asm!("hlt");
-    }
+    sys::interrupt::get_ic().enable_interrupt().unwrap();
+    // Run the tasks of this CPU, kernel threads are switched in on top
+    sys::multitask::Executor::new().run()
 }
 
 unsafe fn test() {
-    let cpuid = CpuId::new().get_feature_info().unwrap().initial_local_apic_id();
+    let cpuid = sys::smp::current_id();
     debug!("Hello World from CPU {}", cpuid);
 }
\ No newline at end of file
@@ -1,22 +1,49 @@
 use crate::boot::BOOTINFO;
+use crate::common::debug::logger;
 use crate::common::debug::symbols::KERNEL_SYMBOLS;
 use crate::common::debug::unwind;
 use crate::common::debug::unwind::trace;
 use crate::common::structs::interrupt;
 use crate::sys;
 use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::lazy_static;
 use log::error;
 
+/// Logical id of the CPU handling the panic, `usize::MAX` if none.
+static PANIC_CPU: AtomicUsize = AtomicUsize::new(usize::MAX);
 static mut PANIC_COUNTER: u32 = 0;
 
+/// Whether a CPU is reporting a panic.
+pub fn is_panicking() -> bool {
+    PANIC_CPU.load(Ordering::Acquire) != usize::MAX
+}
+
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
-    sys::interrupt::get_ic().disable_interrupt().unwrap();
+    // Interrupts may not be set up yet
+    let _ = sys::interrupt::get_ic().disable_interrupt();
+    let cpu = sys::smp::current_id();
+    if let Err(owner) = PANIC_CPU.compare_exchange(usize::MAX, cpu, Ordering::AcqRel, Ordering::Acquire) {
+        if owner != cpu {
+            // Another CPU is reporting, wait for its NMI.
+            hlt()
+        }
+    }
     let panic_counter = unsafe { &mut PANIC_COUNTER };
     *panic_counter += 1;
 
+    let stopped = if *panic_counter == 1 {
+        let stopped = sys::smp::stop::stop_other_cpus();
+        // A stopped CPU may have been holding a log output.
+        unsafe { logger::force_unlock() };
+        stopped
+    } else {
+        sys::smp::stop::stopped_mask()
+    };
+
     error!("⚠!!! KERNEL PANIC !!!⚠");
+    error!("CPU: {}", cpu);
     if let Some(location) = _info.location() {
         error!("Location: {}:{}:{}", location.file(), location.line(), location.column());
     }
@@ -29,12 +56,35 @@ fn panic(_info: &core::panic::PanicInfo) -> ! {
     }
 
     trace::stack_trace();
+    report_other_cpus(cpu, stopped);
     hlt()
 }
 
+/// Print the register state of every other CPU recorded by the stop NMI.
+fn report_other_cpus(current: usize, stopped: sys::smp::CpuMask) {
+    for cpu in sys::smp::cpus().filter(|cpu| *cpu != current) {
+        if !sys::smp::is_online(cpu) {
+            error!("CPU {}: offline", cpu);
+            continue;
+        }
+        match sys::smp::stop::stopped_frame(cpu) {
+            Some(tf) if stopped.contains(cpu) => {
+                KERNEL_SYMBOLS.find_symbol(tf.rip).map(|(function_name, offset)| {
+                    error!("CPU {}: stopped at <{:#x}> - <{:#} + {:#x}>", cpu, tf.rip, function_name, offset);
+                }).unwrap_or_else(|| {
+                    error!("CPU {}: stopped at <{:#x}> - <? + ?>", cpu, tf.rip);
+                });
+                error!("{:#x?}", tf);
+            }
+            _ => error!("CPU {}: did not respond to NMI", cpu),
+        }
+    }
+}
+
 fn hlt() -> ! {
-    unsafe {
-        asm!("hlt");
+    loop {
+        unsafe {
+            asm!("cli; hlt");
+        }
     }
-    loop {}
 }
\ No newline at end of file
@@ -7,6 +7,7 @@ use crate::devices::uart;
 use crate::kinfo;
 use crate::sys::mem;
 use crate::sys::mem::heap;
+use crate::sys::smp;
 
 /// Kernel Boot Stage
 ///
@@ -30,6 +31,7 @@ pub trait KernelInit {
         unsafe {
             trapframe::init();
         }
+        smp::set_online();
         info!("Secondary CPU {} Initialized", cpu.id);
         crate::secondary_main()
     }
@@ -50,6 +52,9 @@ pub trait KernelInit {
 
         // Initialize Memory Subsystem
         heap::module_init();
+
+        // Enumerate Processors
+        smp::module_init();
     }
     fn stage2();
 }
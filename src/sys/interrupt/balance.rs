@@ -0,0 +1,79 @@
+use crate::abstracts::interrupt::controller::IrqAffinity;
+use crate::kinfo::KERNEL_TICK_HZ;
+use crate::sys;
+use crate::sys::interrupt::softirq::Tasklet;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use log::debug;
+
+/// Number of ticks between two balancing rounds.
+const BALANCE_INTERVAL_TICKS: u64 = 10 * KERNEL_TICK_HZ;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BALANCE_TASKLET: Tasklet = Tasklet::new(|_| balance(), 0);
+/// Total count of each GSI seen by the previous balancing round.
+static LAST_COUNTS: IrqSpinLock<BTreeMap<u32, u64>> = IrqSpinLock::new(BTreeMap::new());
+
+/// Start redistributing busy IRQs across online CPUs periodically.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Called on every tick of the BSP, schedules a balancing round once per
+/// interval.
+pub fn tick(jiffies: u64) {
+    if is_enabled() && jiffies % BALANCE_INTERVAL_TICKS == 0 {
+        BALANCE_TASKLET.schedule();
+    }
+}
+
+/// Move at most one IRQ from the busiest CPU to the idlest one.
+///
+/// Load is the number of IRQs a CPU received since the previous round. Only
+/// IRQs bound to a single CPU take part, lowest priority delivery is already
+/// balanced by hardware.
+pub fn balance() {
+    let ic = sys::interrupt::get_ic();
+    let mut last_counts = LAST_COUNTS.lock();
+    let mut loads = vec![0u64; sys::smp::cpu_count()];
+    let mut irqs = Vec::new();
+    for irq in ic.inventory().irqs {
+        let Some(gsi) = irq.gsi else { continue };
+        let total: u64 = irq.counts.iter().sum();
+        let delta = total.saturating_sub(last_counts.insert(gsi, total).unwrap_or(0));
+        if let Ok(IrqAffinity::Cpu(cpu)) = ic.affinity(gsi as usize) {
+            loads[cpu] += delta;
+            irqs.push((gsi, cpu, delta));
+        }
+    }
+
+    let online = sys::smp::online_mask();
+    let Some(busiest) = online.iter().max_by_key(|cpu| loads[*cpu]) else { return };
+    // I/O APIC destinations only hold 8-bit APIC IDs, other CPUs can not
+    // receive a routed IRQ
+    let Some(idlest) = online.iter()
+        .filter(|cpu| sys::smp::apic_id(*cpu) <= u8::MAX as u32)
+        .min_by_key(|cpu| loads[*cpu]) else { return };
+    let gap = loads[busiest] - loads[idlest];
+
+    // Moving an IRQ of `delta` narrows the gap only if `delta < gap`.
+    let candidate = irqs.iter()
+        .filter(|(_, cpu, delta)| *cpu == busiest && *delta > 0 && *delta < gap)
+        .max_by_key(|(_, _, delta)| *delta);
+    if let Some((gsi, _, _)) = candidate {
+        if ic.set_affinity(*gsi as usize, IrqAffinity::Cpu(idlest)).is_ok() {
+            debug!("IRQ balance: moved GSI {} from CPU {} to CPU {}", gsi, busiest, idlest);
+        }
+    }
+}
\ No newline at end of file
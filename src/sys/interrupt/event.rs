@@ -0,0 +1,73 @@
+use crate::sys::sync::atomic_waker::AtomicWaker;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+
+/// An event signalled by an interrupt handler and awaited by a kernel task.
+///
+/// This lets a top half hand its work over to a task which runs with
+/// interrupts enabled:
+///
+/// ```ignore
+/// static RX_EVENT: IrqEvent = IrqEvent::new();
+///
+/// // top half
+/// ic.register_irq_handler(gsi, "rx", Box::new(|_| RX_EVENT.signal()));
+///
+/// // bottom half
+/// sys::multitask::spawn(async {
+///     loop {
+///         RX_EVENT.wait().await;
+///         // drain the device
+///     }
+/// });
+/// ```
+pub struct IrqEvent {
+    pending: AtomicUsize,
+    waker: AtomicWaker,
+}
+
+impl IrqEvent {
+    pub const fn new() -> Self {
+        Self {
+            pending: AtomicUsize::new(0),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    /// Signal the event, safe to call from interrupt context.
+    pub fn signal(&self) {
+        self.pending.fetch_add(1, Ordering::Release);
+        self.waker.wake();
+    }
+
+    /// Wait for the event, resolves to the number of signals since the last
+    /// wait completed.
+    pub fn wait(&self) -> IrqEventFuture<'_> {
+        IrqEventFuture { event: self }
+    }
+}
+
+pub struct IrqEventFuture<'a> {
+    event: &'a IrqEvent,
+}
+
+impl Future for IrqEventFuture<'_> {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let event = self.event;
+        match event.pending.swap(0, Ordering::Acquire) {
+            0 => {}
+            count => return Poll::Ready(count),
+        }
+
+        event.waker.register(cx.waker());
+        // Catch a signal which arrived before the waker was registered
+        match event.pending.swap(0, Ordering::Acquire) {
+            0 => Poll::Pending,
+            count => Poll::Ready(count),
+        }
+    }
+}
\ No newline at end of file
@@ -1,16 +1,46 @@
-use crate::abstracts::interrupt::controller::InterruptController;
+use crate::abstracts::interrupt::controller::{GlobalInterruptController, InterruptController};
+use crate::common::structs::interrupt::manager::IrqResult;
+use alloc::format;
 use alloc::sync::Arc;
+use core::ops::Range;
+use log::info;
 
-static mut IRQ: Option<Arc<dyn InterruptController>> = None;
+pub use crate::arch::hal_impl::ipi::IpiHALImpl as ipi;
 
-pub fn set_ic(irq: Arc<dyn InterruptController>) {
-    unsafe {
-        IRQ = Some(irq);
-    }
+pub mod balance;
+pub mod event;
+pub mod softirq;
+pub mod threaded;
+
+static IRQ: GlobalInterruptController = GlobalInterruptController::new();
+
+/// Add an interrupt controller owning the CPU vectors `vectors`, the first
+/// one registered becomes the primary controller.
+pub fn register_ic(
+    name: &'static str,
+    vectors: Range<usize>,
+    ic: Arc<dyn InterruptController + Send + Sync>,
+) -> IrqResult {
+    IRQ.register(name, vectors, ic)
+}
+
+/// The interrupt controller registry, routing each request to the controller
+/// owning the IRQ.
+pub fn get_ic() -> &'static dyn InterruptController {
+    &IRQ
+}
+
+/// The interrupt controller registry itself, for operations outside
+/// [`InterruptController`].
+pub fn registry() -> &'static GlobalInterruptController {
+    &IRQ
 }
 
-pub fn get_ic() -> Arc<dyn InterruptController> {
-    unsafe {
-        IRQ.as_ref().expect("Interrupt controller not set").clone()
+/// Print the interrupt inventory of the system.
+pub fn dump_inventory() {
+    let inventory = format!("{}", get_ic().inventory());
+    info!("Interrupt Inventory:");
+    for line in inventory.lines() {
+        info!("  {}", line);
     }
 }
\ No newline at end of file
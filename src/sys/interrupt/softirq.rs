@@ -0,0 +1,192 @@
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+
+const SOFTIRQ_NUM: usize = 3;
+/// Rounds of `do_softirq` before the remaining work is left to `run_pending`.
+const MAX_SOFTIRQ_RESTART: usize = 10;
+
+/// Softirq vectors, handled in ascending order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum SoftIrq {
+    Timer = 0,
+    Tasklet = 1,
+    Rcu = 2,
+}
+
+pub type SoftIrqHandler = fn();
+
+struct CpuSoftIrq {
+    /// Bitmap of raised softirqs.
+    pending: AtomicU32,
+    /// Set while the CPU is running softirq handlers.
+    running: AtomicBool,
+    /// Stack of scheduled tasklets.
+    tasklets: AtomicPtr<Tasklet>,
+}
+
+impl CpuSoftIrq {
+    const fn new() -> Self {
+        Self {
+            pending: AtomicU32::new(0),
+            running: AtomicBool::new(false),
+            tasklets: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+/// Handlers are opened while interrupts are live and read on interrupt exit,
+/// so the table is written with interrupts masked.
+static HANDLERS: [IrqSpinLock<Option<SoftIrqHandler>>; SOFTIRQ_NUM] = [
+    IrqSpinLock::new(None),
+    IrqSpinLock::new(Some(run_tasklets as SoftIrqHandler)),
+    IrqSpinLock::new(None),
+];
+static CPU_SOFTIRQS: [CpuSoftIrq; KERNEL_MAX_CPU_NUM] = [const { CpuSoftIrq::new() }; KERNEL_MAX_CPU_NUM];
+
+/// A function deferred from interrupt context, run once on the CPU which
+/// scheduled it with interrupts enabled.
+///
+/// Scheduling an already scheduled tasklet has no effect.
+pub struct Tasklet {
+    func: fn(usize),
+    data: usize,
+    scheduled: AtomicBool,
+    next: AtomicPtr<Tasklet>,
+}
+
+impl Tasklet {
+    pub const fn new(func: fn(usize), data: usize) -> Self {
+        Self {
+            func,
+            data,
+            scheduled: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    pub fn schedule(&'static self) {
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let tasklets = &CPU_SOFTIRQS[sys::smp::current_id()].tasklets;
+        let mut head = tasklets.load(Ordering::Relaxed);
+        loop {
+            self.next.store(head, Ordering::Relaxed);
+            match tasklets.compare_exchange_weak(head, self as *const _ as *mut _, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+        raise_softirq(SoftIrq::Tasklet);
+    }
+}
+
+/// Install the handler of `softirq`.
+pub fn open_softirq(softirq: SoftIrq, handler: SoftIrqHandler) {
+    *HANDLERS[softirq as usize].lock() = Some(handler);
+}
+
+/// Raise `softirq` on the current CPU.
+pub fn raise_softirq(softirq: SoftIrq) {
+    raise_softirq_on(sys::smp::current_id(), softirq);
+}
+
+/// Raise `softirq` on `cpu`, it runs after the next interrupt on that CPU.
+/// Another CPU is interrupted right away, it may be halted with its tick
+/// stopped.
+pub fn raise_softirq_on(cpu: usize, softirq: SoftIrq) {
+    let pending = CPU_SOFTIRQS[cpu].pending.fetch_or(1 << softirq as u32, Ordering::Release);
+    if pending == 0 && cpu != sys::smp::current_id() {
+        sys::smp::send_reschedule(cpu);
+    }
+}
+
+/// Whether softirqs are raised on the current CPU. The idle loops check it
+/// before halting, softirqs raised by the idle path itself would otherwise
+/// wait for an unrelated interrupt.
+pub fn local_pending() -> bool {
+    CPU_SOFTIRQS[sys::smp::current_id()].pending.load(Ordering::Acquire) != 0
+}
+
+/// Run raised softirqs on return from an interrupt handler.
+///
+/// Must be called with interrupts disabled, they are enabled while softirq
+/// handlers run and disabled again before returning.
+pub fn irq_exit() {
+    let state = &CPU_SOFTIRQS[sys::smp::current_id()];
+    if state.pending.load(Ordering::Acquire) != 0 {
+        do_softirq(state);
+    }
+}
+
+/// Whether the current CPU is running softirq handlers.
+pub fn in_softirq() -> bool {
+    CPU_SOFTIRQS[sys::smp::current_id()].running.load(Ordering::Relaxed)
+}
+
+/// Run raised softirqs outside interrupt context, e.g. from the executor.
+///
+/// Must be called with interrupts enabled and outside any critical section,
+/// softirq handlers rely on the nesting depth being zero like on interrupt
+/// exit.
+pub fn run_pending() {
+    let ic = sys::interrupt::registry();
+    ic.disable_interrupt_raw().unwrap();
+    irq_exit();
+    ic.enable_interrupt_raw().unwrap();
+}
+
+fn do_softirq(state: &CpuSoftIrq) {
+    // Softirqs never nest, an interrupt taken while they run leaves its work
+    // to the outer invocation
+    if state.running.swap(true, Ordering::Acquire) {
+        return;
+    }
+
+    // Entered with interrupts disabled by hardware or by an outer critical
+    // section, so the nesting depth must not change here
+    let ic = sys::interrupt::registry();
+    for _ in 0..MAX_SOFTIRQ_RESTART {
+        let pending = state.pending.swap(0, Ordering::Acquire);
+        if pending == 0 {
+            break;
+        }
+
+        ic.enable_interrupt_raw().unwrap();
+        for (softirq, handler) in HANDLERS.iter().enumerate() {
+            if pending & (1 << softirq) != 0 {
+                let handler = *handler.lock();
+                if let Some(handler) = handler {
+                    handler();
+                }
+            }
+        }
+        ic.disable_interrupt_raw().unwrap();
+    }
+
+    state.running.store(false, Ordering::Release);
+}
+
+fn run_tasklets() {
+    let tasklets = &CPU_SOFTIRQS[sys::smp::current_id()].tasklets;
+    let mut list = tasklets.swap(ptr::null_mut(), Ordering::Acquire);
+
+    // Reverse the stack to run tasklets in scheduling order
+    let mut ordered: *mut Tasklet = ptr::null_mut();
+    while let Some(tasklet) = unsafe { list.as_ref() } {
+        let next = tasklet.next.load(Ordering::Relaxed);
+        tasklet.next.store(ordered, Ordering::Relaxed);
+        ordered = list;
+        list = next;
+    }
+
+    while let Some(tasklet) = unsafe { ordered.as_ref() } {
+        ordered = tasklet.next.load(Ordering::Relaxed);
+        tasklet.scheduled.store(false, Ordering::Release);
+        (tasklet.func)(tasklet.data);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+use crate::common::structs::interrupt::manager::{IrqError, IrqResult};
+use crate::sys;
+use crate::sys::interrupt::event::IrqEvent;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, Ordering};
+use log::error;
+
+struct IrqThread {
+    irq: usize,
+    event: IrqEvent,
+    stopped: AtomicBool,
+}
+
+/// Threads of the registered threaded IRQs, keyed by IRQ number.
+static THREADS: IrqSpinLock<BTreeMap<usize, Arc<IrqThread>>> = IrqSpinLock::new(BTreeMap::new());
+
+/// Register `handler` for `irq` to run as its own kernel task.
+///
+/// The hard IRQ only masks the line and wakes the task, which runs `handler`
+/// with interrupts enabled and unmasks the line once the returned future has
+/// completed. The handler may therefore sleep or take locks.
+pub fn request_threaded_irq<F, Fut>(irq: usize, name: &'static str, handler: F) -> IrqResult
+where
+    F: Fn(usize) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output=()> + Send + 'static,
+{
+    let mut threads = THREADS.lock();
+    if threads.contains_key(&irq) {
+        return Err(IrqError::HandlerAlreadyRegistered);
+    }
+
+    let thread = Arc::new(IrqThread {
+        irq,
+        event: IrqEvent::new(),
+        stopped: AtomicBool::new(false),
+    });
+    let hard_irq = thread.clone();
+    sys::interrupt::get_ic().register_irq_handler(irq, name, Box::new(move |_| {
+        // Keep the line masked until the thread is done, a level triggered
+        // line would fire again right after EOI otherwise
+        let _ = sys::interrupt::get_ic().mask_irq(hard_irq.irq);
+        hard_irq.event.signal();
+    }))?;
+    threads.insert(irq, thread.clone());
+
+    sys::multitask::Builder::new().name(format!("irq/{}-{}", irq, name)).spawn(async move {
+        loop {
+            thread.event.wait().await;
+            if thread.stopped.load(Ordering::Acquire) {
+                break;
+            }
+            handler(thread.irq).await;
+            if let Err(err) = sys::interrupt::get_ic().unmask_irq(thread.irq) {
+                error!("Failed to unmask threaded IRQ {}: {:?}", thread.irq, err);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Unregister the threaded handler of `irq` and stop its task.
+///
+/// The line is left masked.
+pub fn free_threaded_irq(irq: usize) -> IrqResult {
+    let thread = THREADS.lock().remove(&irq).ok_or(IrqError::HandlerNotRegistered)?;
+    let ic = sys::interrupt::get_ic();
+    ic.mask_irq(irq)?;
+    ic.unregister_irq_handler(irq)?;
+    thread.stopped.store(true, Ordering::Release);
+    thread.event.signal();
+    Ok(())
+}
\ No newline at end of file
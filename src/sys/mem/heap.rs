@@ -1,10 +1,37 @@
 use crate::boot::BOOTINFO;
-use buddy_system_allocator::LockedHeap;
+use crate::sys::sync::irq_spin_lock::{IrqSpinLock, IrqSpinLockGuard};
+use buddy_system_allocator::Heap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
 use limine::memory_map::EntryType;
 use log::{info, warn};
 
+/// The kernel heap, locked with interrupts disabled. Neither an allocating
+/// interrupt handler nor a thread preempting the holder can spin on it then.
+pub struct KernelHeap(IrqSpinLock<Heap<32>>);
+
+impl KernelHeap {
+    const fn new() -> Self {
+        Self(IrqSpinLock::new(Heap::empty()))
+    }
+
+    pub fn lock(&self) -> IrqSpinLockGuard<'_, Heap<32>> {
+        self.0.lock()
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.lock().alloc(layout).map_or(ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
+
 #[global_allocator]
-pub static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
+pub static HEAP_ALLOCATOR: KernelHeap = KernelHeap::new();
 
 pub fn module_init() {
     let memory_map_response = BOOTINFO.memory_map;
@@ -1,4 +1,7 @@
 pub mod init;
 pub mod interrupt;
-mod multitask;
-pub mod mem;
\ No newline at end of file
+pub mod multitask;
+pub mod mem;
+pub mod smp;
+pub mod sync;
+pub mod time;
\ No newline at end of file
@@ -1,89 +1,254 @@
-use crate::sys::multitask::coordinate::task::{Task, TaskId};
+use crate::abstracts::interrupt::controller::InterruptController;
+use crate::sys;
+use crate::sys::interrupt::softirq;
+use crate::sys::multitask::coordinate::join::{self, JoinHandle};
+use crate::sys::multitask::coordinate::task::{Task, TaskId, TaskState};
+use crate::sys::multitask::thread;
+use crate::sys::smp::percpu;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::sync::rcu;
+use crate::sys::time::nohz;
 use alloc::collections::BTreeMap;
-use alloc::sync::Arc;
-use alloc::task::Wake;
-use core::task::{Context, Waker};
-use crossbeam_queue::ArrayQueue;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_queue::SegQueue;
 
-const QUEUE_CAPACITY: usize = 100;
+/// Ready tasks of a CPU.
+pub(crate) type TaskQueue = SegQueue<Arc<Task>>;
 
-struct TaskWaker {
-    task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+crate::per_cpu! {
+    /// Ready tasks of each CPU. Wakers push to the queue of the owning CPU and
+    /// idle executors steal from the others. The executor of a CPU reaches
+    /// its own through the per-CPU block.
+    static RUN_QUEUES: TaskQueue = SegQueue::new();
+    /// Whether an executor runs on each CPU.
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+    /// Set while the executor of a CPU has run out of work, a task queued for
+    /// it then needs a reschedule IPI.
+    static IDLE: AtomicBool = AtomicBool::new(false);
 }
 
-impl TaskWaker {
-    fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
-        Waker::from(Arc::new(TaskWaker { task_id, task_queue }))
+/// Tasks spawned and not finished yet.
+static TASKS: IrqSpinLock<BTreeMap<TaskId, Weak<Task>>> = IrqSpinLock::new(BTreeMap::new());
+
+/// Snapshot of a live task, see [`tasks`].
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub name: Option<String>,
+    pub state: TaskState,
+    /// CPU whose run queue the task goes to when woken.
+    pub cpu: usize,
+}
+
+/// Configures a task before spawning it.
+#[derive(Default)]
+pub struct Builder {
+    name: Option<String>,
+    cpu: Option<usize>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    fn wake_task(&self) {
-        self.task_queue.push(self.task_id).expect("task_queue full");
+    /// Name the task, shown by [`tasks`].
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
     }
-}
 
-impl Wake for TaskWaker {
-    fn wake(self: Arc<Self>) {
-        self.wake_task();
+    /// Queue the task on `cpu` instead of the current one.
+    pub fn cpu(mut self, cpu: usize) -> Self {
+        self.cpu = Some(cpu);
+        self
     }
 
-    fn wake_by_ref(self: &Arc<Self>) {
-        self.wake_task();
+    pub fn spawn<F>(self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let cpu = self.cpu.unwrap_or_else(sys::smp::current_id);
+        let (future, state) = join::wrap(future);
+        let task = Task::new(future, self.name, cpu);
+        TASKS.lock().insert(task.id, Arc::downgrade(&task));
+        enqueue(task.clone());
+        JoinHandle::new(task, state)
     }
 }
 
+/// Spawn `future` as a new task on the executor of the current CPU.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Builder::new().spawn(future)
+}
+
+/// Spawn `future` as a new task on the executor of `cpu`. Until that CPU runs
+/// an executor, the task is left to the others to steal.
+pub fn spawn_on<F>(cpu: usize, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Builder::new().cpu(cpu).spawn(future)
+}
+
+/// List the tasks which have not completed or been cancelled yet.
+pub fn tasks() -> Vec<TaskInfo> {
+    TASKS.lock().values()
+        .filter_map(Weak::upgrade)
+        .map(|task| TaskInfo {
+            id: task.id,
+            name: task.name().map(String::from),
+            state: task.state(),
+            cpu: task.owner(),
+        })
+        .collect()
+}
+
+/// Forget a finished task.
+pub(super) fn unregister(id: TaskId) {
+    TASKS.lock().remove(&id);
+}
+
+/// Queue a woken task on the CPU owning it.
+pub(super) fn enqueue(task: Arc<Task>) {
+    if !task.mark_queued() {
+        return;
+    }
+    let owner = task.owner();
+    RUN_QUEUES.of(owner).push(task);
+    if owner != sys::smp::current_id() && IDLE.of(owner).load(Ordering::Acquire) {
+        sys::smp::send_reschedule(owner);
+    }
+}
+
+/// The executor of a CPU, polling the tasks of its run queue and stealing
+/// from the other CPUs once it runs dry.
 pub struct Executor {
-    tasks: BTreeMap<TaskId, Task>,
-    task_queue: Arc<ArrayQueue<TaskId>>,
-    waker_cache: BTreeMap<TaskId, Waker>,
+    cpu: usize,
 }
 
 impl Executor {
+    /// Create the executor of the current CPU, the calling thread must stay on
+    /// it.
     pub fn new() -> Self {
-        Executor {
-            tasks: BTreeMap::new(),
-            task_queue: Arc::new(ArrayQueue::new(QUEUE_CAPACITY)),
-            waker_cache: BTreeMap::new(),
-        }
+        let cpu = sys::smp::current_id();
+        assert!(!ACTIVE.of(cpu).swap(true, Ordering::AcqRel), "Executor already running on CPU {}", cpu);
+        percpu::this().set_run_queue(RUN_QUEUES.of(cpu));
+        Executor { cpu }
     }
 
-    pub fn spawn(&mut self, task: Task) {
-        let task_id = task.id;
-        if self.tasks.insert(task_id, task).is_some() {
-            panic!("Task with same ID already in tasks");
-        }
-        self.task_queue.push(task_id).expect("queue full");
+    /// Run queue of this executor, read from the per-CPU block.
+    fn queue(&self) -> &'static TaskQueue {
+        percpu::run_queue().expect("No executor running on this CPU")
+    }
+
+    /// Spawn `future` on this executor.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        spawn_on(self.cpu, future)
     }
 
     pub fn run(&mut self) -> ! {
         loop {
-            self.run_ready_tasks();
+            // Softirqs deferred by a busy interrupt handler run here
+            softirq::run_pending();
+            if self.run_ready_tasks() == 0 && !self.steal() {
+                self.idle();
+            }
         }
     }
 
-    pub fn sleep_if_idle(&self) {
-        // TODO: Implement this after interrupt subsystem is implemented
-        unimplemented!()
+    /// Let other kernel threads run, then halt until a task is queued here.
+    /// Wakers on other CPUs send a reschedule IPI meanwhile.
+    fn idle(&self) {
+        IDLE.of(self.cpu).store(true, Ordering::Release);
+        // A task queued before the flag was set sent no IPI
+        if self.queue().is_empty() {
+            thread::yield_now();
+            self.sleep_if_idle();
+        }
+        IDLE.of(self.cpu).store(false, Ordering::Release);
     }
 
-    fn run_ready_tasks(&mut self) {
-        let Self { tasks, task_queue, waker_cache } = self;
+    /// Halt the CPU until the next interrupt if no task is ready.
+    ///
+    /// The run queue is checked with interrupts disabled, and they are only
+    /// enabled again together with the halt, so a task woken by an interrupt
+    /// handler right after the check still ends the halt. The interrupt flag
+    /// is restored on return.
+    fn sleep_if_idle(&self) {
+        let ic = sys::interrupt::registry();
+        let enabled = ic.is_interrupt_enabled();
+        // The nesting counter stays untouched, `wait_for_interrupt` enables
+        // interrupts behind its back
+        let _ = ic.disable_interrupt_raw();
+        if self.queue().is_empty() {
+            nohz::idle_enter();
+            // Softirqs raised by entering idle run in `run` instead
+            if !softirq::local_pending() {
+                ic.wait_for_interrupt();
+                let _ = ic.disable_interrupt_raw();
+            }
+            nohz::idle_exit();
+            // A reschedule IPI could not preempt the halt
+            thread::scheduler::preempt_if_requested();
+        }
+        if enabled {
+            let _ = ic.enable_interrupt_raw();
+        }
+    }
 
-        while let Some(task_id) = task_queue.pop() {
-            let task = match tasks.get_mut(&task_id) {
-                Some(task) => task,
-                None => continue
-            };
+    /// Poll the tasks queued so far, so a task waking itself forever does not
+    /// starve softirqs and stealing. Returns the number of tasks polled.
+    fn run_ready_tasks(&mut self) -> usize {
+        let queue = self.queue();
+        let mut polled = 0;
+        for _ in 0..queue.len() {
+            let Some(task) = queue.pop() else { break };
+            task.set_owner(self.cpu);
+            match task.poll() {
+                Some(_) => {
+                    polled += 1;
+                    // A task can not hold a read section across polls
+                    rcu::note_quiescent_state();
+                }
+                // Still being polled by the CPU it was stolen from
+                None => queue.push(task),
+            }
+        }
+        polled
+    }
 
-            let waker = waker_cache
-                .entry(task_id)
-                .or_insert_with(|| TaskWaker::new(task_id, task_queue.clone()));
+    /// Move half of the tasks of the busiest CPU into the local run queue.
+    fn steal(&self) -> bool {
+        let victim = sys::smp::online_mask().iter()
+            .filter(|&cpu| cpu != self.cpu)
+            .max_by_key(|&cpu| RUN_QUEUES.of(cpu).len())
+            .filter(|&cpu| !RUN_QUEUES.of(cpu).is_empty());
+        let Some(victim) = victim else { return false };
 
-            let mut context = Context::from_waker(waker);
-            match task.poll(&mut context) {
-                core::task::Poll::Pending => {}
-                core::task::Poll::Ready(()) => { tasks.remove(&task_id); }
-            }
+        let count = RUN_QUEUES.of(victim).len().div_ceil(2);
+        let queue = self.queue();
+        let mut stolen = false;
+        for _ in 0..count {
+            let Some(task) = RUN_QUEUES.of(victim).pop() else { break };
+            task.set_owner(self.cpu);
+            queue.push(task);
+            stolen = true;
         }
+        stolen
     }
 }
\ No newline at end of file
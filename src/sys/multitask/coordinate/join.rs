@@ -0,0 +1,112 @@
+use crate::sys::multitask::coordinate::task::{Task, TaskId, TaskState};
+use crate::sys::sync::atomic_waker::AtomicWaker;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was cancelled before it completed.
+    Cancelled,
+}
+
+pub(super) struct JoinState<T> {
+    output: IrqSpinLock<Option<T>>,
+    /// Set once the future completed or was dropped.
+    finished: AtomicBool,
+    waker: AtomicWaker,
+}
+
+/// Marks the task finished when the wrapped future completes or is dropped
+/// by a cancellation.
+struct Completion<T> {
+    state: Arc<JoinState<T>>,
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        self.state.finished.store(true, Ordering::Release);
+        self.state.waker.wake();
+    }
+}
+
+/// Wrap `future` so its output is handed to the returned state.
+pub(super) fn wrap<F>(future: F) -> (impl Future<Output=()> + Send + 'static, Arc<JoinState<F::Output>>)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let state = Arc::new(JoinState {
+        output: IrqSpinLock::new(None),
+        finished: AtomicBool::new(false),
+        waker: AtomicWaker::new(),
+    });
+    let completion = Completion { state: state.clone() };
+    let wrapped = async move {
+        let output = future.await;
+        *completion.state.output.lock() = Some(output);
+        drop(completion);
+    };
+    (wrapped, state)
+}
+
+/// Owned permission to await or cancel a spawned task. Dropping the handle
+/// detaches the task.
+pub struct JoinHandle<T> {
+    task: Arc<Task>,
+    state: Arc<JoinState<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(super) fn new(task: Arc<Task>, state: Arc<JoinState<T>>) -> Self {
+        Self { task, state }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.task.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.task.name()
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.task.state()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Acquire)
+    }
+
+    /// Cancel the task, awaiting the handle then resolves to
+    /// [`JoinError::Cancelled`] unless the task completed first.
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    fn try_take(&self) -> Option<Result<T, JoinError>> {
+        if !self.is_finished() {
+            return None;
+        }
+        Some(self.state.output.lock().take().ok_or(JoinError::Cancelled))
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(result) = self.try_take() {
+            return Poll::Ready(result);
+        }
+        self.state.waker.register(cx.waker());
+        // Catch a completion which happened before the waker was registered
+        match self.try_take() {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
\ No newline at end of file
@@ -1,2 +1,6 @@
 mod task;
-pub mod executor;
\ No newline at end of file
+mod join;
+pub mod executor;
+
+pub use join::{JoinError, JoinHandle};
+pub use task::{TaskId, TaskState};
\ No newline at end of file
@@ -1,11 +1,16 @@
+use crate::sys::multitask::coordinate::executor;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::task::Wake;
 use core::future::Future;
 use core::pin::Pin;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use core::task::{Context, Poll};
+use spin::Mutex;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
-pub(super) struct TaskId(usize);
+pub struct TaskId(usize);
 
 impl TaskId {
     fn new() -> TaskId {
@@ -14,20 +19,140 @@ impl TaskId {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TaskState {
+    /// Waiting in a run queue.
+    Ready = 0,
+    /// Being polled.
+    Running = 1,
+    /// Waiting for a wakeup.
+    Waiting = 2,
+    Completed = 3,
+    Cancelled = 4,
+}
+
+impl TaskState {
+    fn from_u8(state: u8) -> Self {
+        match state {
+            0 => Self::Ready,
+            1 => Self::Running,
+            2 => Self::Waiting,
+            3 => Self::Completed,
+            _ => Self::Cancelled,
+        }
+    }
+}
+
+/// A future scheduled on the executors. It is its own waker, waking it puts
+/// it back into the run queue of the CPU owning it.
 pub struct Task {
     pub id: TaskId,
-    future: Pin<Box<dyn Future<Output=()>>>,
+    name: Option<String>,
+    state: AtomicU8,
+    cancelled: AtomicBool,
+    /// `None` once the future has completed or was cancelled. Only ever taken
+    /// with `try_lock`, so no CPU spins on it while its holder is preempted.
+    future: Mutex<Option<Pin<Box<dyn Future<Output=()> + Send>>>>,
+    /// CPU whose run queue the task goes to when woken, the last one which
+    /// polled it.
+    owner: AtomicUsize,
+    /// Set while the task sits in a run queue, so repeated wakeups queue it
+    /// only once.
+    queued: AtomicBool,
 }
 
 impl Task {
-    pub fn new(future: impl Future<Output=()> + 'static) -> Task {
-        Task {
+    pub fn new(future: impl Future<Output=()> + Send + 'static, name: Option<String>, owner: usize) -> Arc<Task> {
+        Arc::new(Task {
             id: TaskId::new(),
-            future: Box::pin(future),
+            name,
+            state: AtomicU8::new(TaskState::Ready as u8),
+            cancelled: AtomicBool::new(false),
+            future: Mutex::new(Some(Box::pin(future))),
+            owner: AtomicUsize::new(owner),
+            queued: AtomicBool::new(false),
+        })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn state(&self) -> TaskState {
+        TaskState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    fn set_state(&self, state: TaskState) {
+        self.state.store(state as u8, Ordering::Release);
+    }
+
+    pub fn owner(&self) -> usize {
+        self.owner.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_owner(&self, cpu: usize) {
+        self.owner.store(cpu, Ordering::Relaxed);
+    }
+
+    /// Mark the task queued, returns `false` if it already was.
+    pub(super) fn mark_queued(&self) -> bool {
+        if self.queued.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        // A finished task stays finished, the wakeup only drops it
+        let _ = self.state.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+            (state == TaskState::Waiting as u8).then_some(TaskState::Ready as u8)
+        });
+        true
+    }
+
+    /// Drop the future on its executor at the next poll, the task never runs
+    /// again.
+    pub fn cancel(self: &Arc<Self>) {
+        self.cancelled.store(true, Ordering::Release);
+        executor::enqueue(self.clone());
+    }
+
+    /// Poll the future on behalf of the executor.
+    ///
+    /// Returns `None` if another CPU is polling it right now, the caller must
+    /// queue it again so the wakeup is not lost.
+    pub(super) fn poll(self: &Arc<Self>) -> Option<Poll<()>> {
+        let mut future = self.future.try_lock()?;
+        // Cleared before polling, a wakeup from inside `poll` queues it again
+        self.queued.store(false, Ordering::Release);
+        let Some(inner) = future.as_mut() else { return Some(Poll::Ready(())) };
+        if self.cancelled.load(Ordering::Acquire) {
+            *future = None;
+            self.set_state(TaskState::Cancelled);
+            executor::unregister(self.id);
+            return Some(Poll::Ready(()));
+        }
+
+        self.set_state(TaskState::Running);
+        let waker = self.clone().into();
+        let mut context = Context::from_waker(&waker);
+        let result = inner.as_mut().poll(&mut context);
+        if result.is_ready() {
+            *future = None;
+            self.set_state(TaskState::Completed);
+            executor::unregister(self.id);
+        } else if self.queued.load(Ordering::Acquire) {
+            self.set_state(TaskState::Ready);
+        } else {
+            self.set_state(TaskState::Waiting);
         }
+        Some(result)
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        executor::enqueue(self);
     }
 
-    pub(super) fn poll(&mut self, context: &mut Context) -> Poll<()> {
-        self.future.as_mut().poll(context)
+    fn wake_by_ref(self: &Arc<Self>) {
+        executor::enqueue(self.clone());
     }
 }
\ No newline at end of file
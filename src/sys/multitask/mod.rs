@@ -1 +1,16 @@
-mod coordinate;
\ No newline at end of file
+use log::info;
+
+mod coordinate;
+pub mod thread;
+pub mod workqueue;
+
+pub use coordinate::executor::{spawn, spawn_on, tasks, Builder, Executor, TaskInfo};
+pub(crate) use coordinate::executor::TaskQueue;
+pub use coordinate::{JoinError, JoinHandle, TaskId, TaskState};
+pub use crate::arch::hal_impl::context::ContextHALImpl as context;
+
+pub fn module_init() {
+    info!("☞ Hikari Multitask Module");
+    thread::scheduler::init_cpu();
+    workqueue::init();
+}
@@ -0,0 +1,369 @@
+use crate::sys::multitask::thread::{Thread, ThreadId};
+use crate::sys::time::tick::TICK_NSEC;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+
+/// Highest real-time priority, the lowest is 1.
+pub const RT_PRIO_MAX: u8 = 99;
+pub const NICE_MIN: i8 = -20;
+pub const NICE_MAX: i8 = 19;
+/// Ticks a round robin thread runs before the next one of its priority.
+pub const RR_TIMESLICE_TICKS: u64 = 10;
+
+/// Period in which every runnable fair thread should run once.
+const SCHED_LATENCY_NS: u64 = 4 * TICK_NSEC;
+/// Shortest slice of a fair thread, however many are runnable.
+const MIN_GRANULARITY_NS: u64 = TICK_NSEC;
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// Load weight of each nice value, every step is worth about 10% CPU time.
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291,
+    29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906,
+    3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423,
+    335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45,
+    36, 29, 23, 18, 15,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedError {
+    /// Real-time priority outside `1..=RT_PRIO_MAX`.
+    InvalidPriority,
+    /// Nice value outside `NICE_MIN..=NICE_MAX`.
+    InvalidNice,
+}
+
+pub type SchedResult<T = ()> = Result<T, SchedError>;
+
+/// How a thread is scheduled. Real-time threads always run before fair ones,
+/// which run before idle ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// Real-time, runs until it blocks, yields or a higher priority thread
+    /// becomes runnable.
+    Fifo(u8),
+    /// Real-time like `Fifo`, but handing the CPU to the next thread of the
+    /// same priority every `RR_TIMESLICE_TICKS`.
+    RoundRobin(u8),
+    /// Fair time sharing, the CPU time is split by the weight of the nice
+    /// value.
+    Normal(i8),
+    /// Runs only when no other thread is runnable.
+    Idle,
+}
+
+impl Default for SchedPolicy {
+    fn default() -> Self {
+        Self::Normal(0)
+    }
+}
+
+impl SchedPolicy {
+    pub fn validate(&self) -> SchedResult {
+        match *self {
+            Self::Fifo(prio) | Self::RoundRobin(prio) if prio == 0 || prio > RT_PRIO_MAX => Err(SchedError::InvalidPriority),
+            Self::Normal(nice) if !(NICE_MIN..=NICE_MAX).contains(&nice) => Err(SchedError::InvalidNice),
+            _ => Ok(()),
+        }
+    }
+
+    /// Order in which policies are served, higher first.
+    pub(super) fn rank(&self) -> u32 {
+        match *self {
+            Self::Idle => 0,
+            Self::Normal(_) => 1,
+            Self::Fifo(prio) | Self::RoundRobin(prio) => 1 + prio as u32,
+        }
+    }
+
+    fn weight(&self) -> u64 {
+        match *self {
+            Self::Normal(nice) => NICE_TO_WEIGHT[(nice - NICE_MIN) as usize],
+            _ => NICE_0_WEIGHT,
+        }
+    }
+}
+
+/// Scheduling state of a thread, locked after the run queue.
+pub(super) struct SchedEntity {
+    pub policy: SchedPolicy,
+    /// Runtime of a fair thread scaled by its weight.
+    vruntime: u64,
+    /// `vruntime` the thread was queued with in the fair class.
+    queued_vruntime: u64,
+    /// Total CPU time consumed.
+    pub sum_exec_ns: u64,
+    /// `sum_exec_ns` when the thread was last picked.
+    slice_start_ns: u64,
+    rr_ticks_left: u64,
+    /// Set by `yield_now`, the thread goes behind the other queued threads.
+    pub yielding: bool,
+}
+
+impl SchedEntity {
+    pub const fn new() -> Self {
+        Self {
+            policy: SchedPolicy::Normal(0),
+            vruntime: 0,
+            queued_vruntime: 0,
+            sum_exec_ns: 0,
+            slice_start_ns: 0,
+            rr_ticks_left: RR_TIMESLICE_TICKS,
+            yielding: false,
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: SchedPolicy) {
+        self.policy = policy;
+        self.rr_ticks_left = RR_TIMESLICE_TICKS;
+    }
+
+    /// Charge `delta_ns` of CPU time to the thread.
+    pub fn account(&mut self, delta_ns: u64) {
+        self.sum_exec_ns += delta_ns;
+        if let SchedPolicy::Normal(_) = self.policy {
+            self.vruntime += delta_ns * NICE_0_WEIGHT / self.policy.weight();
+        }
+    }
+
+    /// Start a new slice, the thread was picked to run.
+    pub fn start_slice(&mut self) {
+        self.slice_start_ns = self.sum_exec_ns;
+        if self.rr_ticks_left == 0 {
+            self.rr_ticks_left = RR_TIMESLICE_TICKS;
+        }
+    }
+}
+
+/// A scheduling class, holding the runnable threads of its policies.
+trait SchedClass {
+    fn enqueue(&mut self, thread: Arc<Thread>, entity: &mut SchedEntity);
+
+    /// Remove a queued thread, returns `false` if it was not queued.
+    fn dequeue(&mut self, thread: &Thread, entity: &SchedEntity) -> bool;
+
+    /// Take the next thread allowed to run on `cpu`.
+    fn pick_next(&mut self, cpu: usize) -> Option<Arc<Thread>>;
+
+    /// Rank of the best thread allowed to run on `cpu`.
+    fn highest_rank(&self, cpu: usize) -> Option<u32>;
+
+    /// Account a tick to the running thread, returns whether its slice is
+    /// used up.
+    fn task_tick(&self, entity: &mut SchedEntity) -> bool;
+}
+
+/// Fifo and round robin threads, a queue per priority.
+struct RtClass {
+    queues: [VecDeque<Arc<Thread>>; RT_PRIO_MAX as usize + 1],
+}
+
+impl RtClass {
+    const fn new() -> Self {
+        Self { queues: [const { VecDeque::new() }; RT_PRIO_MAX as usize + 1] }
+    }
+
+    fn prio(policy: SchedPolicy) -> usize {
+        match policy {
+            SchedPolicy::Fifo(prio) | SchedPolicy::RoundRobin(prio) => prio as usize,
+            _ => unreachable!("Not a real-time policy: {:?}", policy),
+        }
+    }
+}
+
+impl SchedClass for RtClass {
+    fn enqueue(&mut self, thread: Arc<Thread>, entity: &mut SchedEntity) {
+        entity.yielding = false;
+        self.queues[Self::prio(entity.policy)].push_back(thread);
+    }
+
+    fn dequeue(&mut self, thread: &Thread, entity: &SchedEntity) -> bool {
+        let queue = &mut self.queues[Self::prio(entity.policy)];
+        match queue.iter().position(|queued| queued.id == thread.id) {
+            Some(index) => queue.remove(index).is_some(),
+            None => false,
+        }
+    }
+
+    fn pick_next(&mut self, cpu: usize) -> Option<Arc<Thread>> {
+        self.queues.iter_mut().rev().find_map(|queue| {
+            let index = queue.iter().position(|thread| thread.runs_on(cpu))?;
+            queue.remove(index)
+        })
+    }
+
+    fn highest_rank(&self, cpu: usize) -> Option<u32> {
+        let prio = self.queues.iter().rposition(|queue| queue.iter().any(|thread| thread.runs_on(cpu)))?;
+        Some(1 + prio as u32)
+    }
+
+    fn task_tick(&self, entity: &mut SchedEntity) -> bool {
+        match entity.policy {
+            SchedPolicy::RoundRobin(_) => {
+                entity.rr_ticks_left = entity.rr_ticks_left.saturating_sub(1);
+                entity.rr_ticks_left == 0
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Normal threads, ordered by virtual runtime so the one which got the least
+/// CPU time for its weight runs first.
+struct FairClass {
+    queue: BTreeMap<(u64, ThreadId), (Arc<Thread>, u64)>,
+    /// Monotonic floor of the virtual runtime of the queued threads.
+    min_vruntime: u64,
+    /// Sum of the weights of the queued threads.
+    total_weight: u64,
+}
+
+impl FairClass {
+    const fn new() -> Self {
+        Self { queue: BTreeMap::new(), min_vruntime: 0, total_weight: 0 }
+    }
+}
+
+impl SchedClass for FairClass {
+    fn enqueue(&mut self, thread: Arc<Thread>, entity: &mut SchedEntity) {
+        // A thread which slept long does not get to monopolize the CPU, it
+        // starts a bit ahead of the others
+        entity.vruntime = entity.vruntime.max(self.min_vruntime.saturating_sub(SCHED_LATENCY_NS / 2));
+        if core::mem::take(&mut entity.yielding) {
+            if let Some(&(last, _)) = self.queue.keys().next_back() {
+                entity.vruntime = entity.vruntime.max(last + 1);
+            }
+        }
+        entity.queued_vruntime = entity.vruntime;
+        let weight = entity.policy.weight();
+        self.total_weight += weight;
+        self.queue.insert((entity.vruntime, thread.id), (thread, weight));
+    }
+
+    fn dequeue(&mut self, thread: &Thread, entity: &SchedEntity) -> bool {
+        match self.queue.remove(&(entity.queued_vruntime, thread.id)) {
+            Some((_, weight)) => {
+                self.total_weight -= weight;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn pick_next(&mut self, cpu: usize) -> Option<Arc<Thread>> {
+        let key = *self.queue.iter().find(|(_, (thread, _))| thread.runs_on(cpu))?.0;
+        let (thread, weight) = self.queue.remove(&key)?;
+        self.total_weight -= weight;
+        self.min_vruntime = self.min_vruntime.max(key.0);
+        Some(thread)
+    }
+
+    fn highest_rank(&self, cpu: usize) -> Option<u32> {
+        self.queue.values().any(|(thread, _)| thread.runs_on(cpu)).then_some(SchedPolicy::Normal(0).rank())
+    }
+
+    fn task_tick(&self, entity: &mut SchedEntity) -> bool {
+        let weight = entity.policy.weight();
+        let slice = (SCHED_LATENCY_NS * weight / (self.total_weight + weight)).max(MIN_GRANULARITY_NS);
+        entity.sum_exec_ns - entity.slice_start_ns >= slice
+    }
+}
+
+/// Idle policy threads, run in turn whenever nothing else is runnable.
+struct IdleClass {
+    queue: VecDeque<Arc<Thread>>,
+}
+
+impl SchedClass for IdleClass {
+    fn enqueue(&mut self, thread: Arc<Thread>, entity: &mut SchedEntity) {
+        entity.yielding = false;
+        self.queue.push_back(thread);
+    }
+
+    fn dequeue(&mut self, thread: &Thread, _entity: &SchedEntity) -> bool {
+        match self.queue.iter().position(|queued| queued.id == thread.id) {
+            Some(index) => self.queue.remove(index).is_some(),
+            None => false,
+        }
+    }
+
+    fn pick_next(&mut self, cpu: usize) -> Option<Arc<Thread>> {
+        let index = self.queue.iter().position(|thread| thread.runs_on(cpu))?;
+        self.queue.remove(index)
+    }
+
+    fn highest_rank(&self, cpu: usize) -> Option<u32> {
+        self.queue.iter().any(|thread| thread.runs_on(cpu)).then_some(SchedPolicy::Idle.rank())
+    }
+
+    fn task_tick(&self, _entity: &mut SchedEntity) -> bool {
+        false
+    }
+}
+
+/// Runnable threads of every class.
+pub(super) struct RunQueue {
+    rt: RtClass,
+    fair: FairClass,
+    idle: IdleClass,
+}
+
+impl RunQueue {
+    pub const fn new() -> Self {
+        Self {
+            rt: RtClass::new(),
+            fair: FairClass::new(),
+            idle: IdleClass { queue: VecDeque::new() },
+        }
+    }
+
+    /// Classes in the order they are served.
+    fn classes(&self) -> [&dyn SchedClass; 3] {
+        [&self.rt, &self.fair, &self.idle]
+    }
+
+    fn class_of(&mut self, policy: SchedPolicy) -> &mut dyn SchedClass {
+        match policy {
+            SchedPolicy::Fifo(_) | SchedPolicy::RoundRobin(_) => &mut self.rt,
+            SchedPolicy::Normal(_) => &mut self.fair,
+            SchedPolicy::Idle => &mut self.idle,
+        }
+    }
+
+    pub fn enqueue(&mut self, thread: Arc<Thread>) {
+        let owner = thread.clone();
+        let mut entity = owner.sched.lock();
+        self.class_of(entity.policy).enqueue(thread, &mut entity);
+    }
+
+    pub fn dequeue(&mut self, thread: &Thread) -> bool {
+        let entity = thread.sched.lock();
+        self.class_of(entity.policy).dequeue(thread, &entity)
+    }
+
+    pub fn pick_next(&mut self, cpu: usize) -> Option<Arc<Thread>> {
+        self.rt.pick_next(cpu)
+            .or_else(|| self.fair.pick_next(cpu))
+            .or_else(|| self.idle.pick_next(cpu))
+    }
+
+    /// Rank of the best thread allowed to run on `cpu`, see
+    /// [`SchedPolicy::rank`].
+    pub fn highest_rank(&self, cpu: usize) -> Option<u32> {
+        self.classes().iter().find_map(|class| class.highest_rank(cpu))
+    }
+
+    /// Account a tick to the thread running on `cpu`, returns whether it
+    /// should be preempted.
+    pub fn task_tick(&self, cpu: usize, entity: &mut SchedEntity) -> bool {
+        let expired = match entity.policy {
+            SchedPolicy::Fifo(_) | SchedPolicy::RoundRobin(_) => self.rt.task_tick(entity),
+            SchedPolicy::Normal(_) => self.fair.task_tick(entity),
+            SchedPolicy::Idle => self.idle.task_tick(entity),
+        };
+        expired || self.highest_rank(cpu).is_some_and(|rank| rank > entity.policy.rank())
+    }
+}
\ No newline at end of file
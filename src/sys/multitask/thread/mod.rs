@@ -0,0 +1,299 @@
+use crate::abstracts::context::ContextHAL;
+use crate::sys::multitask::context;
+use crate::sys::multitask::thread::class::SchedEntity;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::time::{clocksource, timer};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use core::cell::UnsafeCell;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use core::time::Duration;
+
+mod class;
+pub mod scheduler;
+
+pub use class::{SchedError, SchedPolicy, SchedResult, NICE_MAX, NICE_MIN, RR_TIMESLICE_TICKS, RT_PRIO_MAX};
+
+/// Size of the stack of a kernel thread.
+pub const THREAD_STACK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+pub struct ThreadId(usize);
+
+impl ThreadId {
+    fn new() -> ThreadId {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ThreadState {
+    /// Waiting in the run queue.
+    Ready = 0,
+    Running = 1,
+    /// Parked until `unpark`.
+    Blocked = 2,
+    /// Exited, never runs again.
+    Dead = 3,
+}
+
+impl ThreadState {
+    fn from_u8(state: u8) -> Self {
+        match state {
+            0 => Self::Ready,
+            1 => Self::Running,
+            2 => Self::Blocked,
+            _ => Self::Dead,
+        }
+    }
+}
+
+type ThreadEntry = Box<dyn FnOnce() + Send>;
+
+/// A kernel thread with its own stack.
+pub struct Thread {
+    id: ThreadId,
+    name: String,
+    state: AtomicU8,
+    /// Set while a CPU runs on the stack of the thread, including the switch
+    /// away from it.
+    on_cpu: AtomicBool,
+    /// Wakeup token of `park`/`unpark`.
+    unparked: AtomicBool,
+    /// Idle threads are picked only when the run queue is empty.
+    idle: bool,
+    /// CPU the thread is bound to, if any.
+    affinity: Option<usize>,
+    sched: IrqSpinLock<SchedEntity>,
+    context: UnsafeCell<<context as ContextHAL>::Context>,
+    /// Stack of the thread, `None` for the boot context of a CPU.
+    stack: Option<Box<[u8]>>,
+    entry: IrqSpinLock<Option<ThreadEntry>>,
+}
+
+// The context is only touched by the CPU switching from or to the thread
+unsafe impl Sync for Thread {}
+
+impl Thread {
+    fn new(name: &str, idle: bool, affinity: Option<usize>, entry: ThreadEntry) -> Arc<Self> {
+        let stack = vec![0u8; THREAD_STACK_SIZE].into_boxed_slice();
+        let stack_top = stack.as_ptr() as usize + stack.len();
+        let context = unsafe { context::new_context(stack_top, thread_entry, 0) };
+        Arc::new(Self {
+            id: ThreadId::new(),
+            name: name.to_string(),
+            state: AtomicU8::new(ThreadState::Ready as u8),
+            on_cpu: AtomicBool::new(false),
+            unparked: AtomicBool::new(false),
+            idle,
+            affinity,
+            sched: IrqSpinLock::new(SchedEntity::new()),
+            context: UnsafeCell::new(context),
+            stack: Some(stack),
+            entry: IrqSpinLock::new(Some(entry)),
+        })
+    }
+
+    /// Adopt the context `cpu` is running on, its registers are saved on the
+    /// first switch away from it. The thread stays bound to `cpu`.
+    fn bootstrap(name: &str, cpu: usize) -> Arc<Self> {
+        Arc::new(Self {
+            id: ThreadId::new(),
+            name: name.to_string(),
+            state: AtomicU8::new(ThreadState::Running as u8),
+            on_cpu: AtomicBool::new(true),
+            unparked: AtomicBool::new(false),
+            idle: false,
+            affinity: Some(cpu),
+            sched: IrqSpinLock::new(SchedEntity::new()),
+            context: UnsafeCell::new(Default::default()),
+            stack: None,
+            entry: IrqSpinLock::new(None),
+        })
+    }
+
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Address range of the stack, `None` for the boot context of a CPU.
+    pub fn stack_range(&self) -> Option<Range<usize>> {
+        self.stack.as_ref().map(|stack| stack.as_ptr_range()).map(|range| range.start as usize..range.end as usize)
+    }
+
+    pub fn affinity(&self) -> Option<usize> {
+        self.affinity
+    }
+
+    fn runs_on(&self, cpu: usize) -> bool {
+        self.affinity.map_or(true, |affinity| affinity == cpu)
+    }
+
+    pub fn policy(&self) -> SchedPolicy {
+        self.sched.lock().policy
+    }
+
+    /// Change how the thread is scheduled, taking effect at once if it is
+    /// runnable.
+    pub fn set_policy(self: &Arc<Self>, policy: SchedPolicy) -> SchedResult {
+        policy.validate()?;
+        scheduler::set_policy(self, policy);
+        Ok(())
+    }
+
+    /// CPU time consumed so far, updated on every tick and switch.
+    pub fn cpu_time(&self) -> Duration {
+        Duration::from_nanos(self.sched.lock().sum_exec_ns)
+    }
+
+    pub fn state(&self) -> ThreadState {
+        ThreadState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    fn set_state(&self, state: ThreadState) {
+        self.state.store(state as u8, Ordering::Release);
+    }
+
+    fn transition(&self, from: ThreadState, to: ThreadState) -> bool {
+        self.state.compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire).is_ok()
+    }
+
+    /// Make the thread runnable again if it is parked, otherwise its next
+    /// `park` returns immediately.
+    pub fn unpark(self: &Arc<Self>) {
+        self.unparked.store(true, Ordering::Release);
+        if self.transition(ThreadState::Blocked, ThreadState::Ready) {
+            scheduler::enqueue(self.clone());
+        }
+    }
+}
+
+extern "C" fn thread_entry(_arg: usize) -> ! {
+    scheduler::finish_switch();
+    let entry = current().entry.lock().take();
+    // Switched to from `schedule`, always with interrupts disabled
+    let _ = crate::sys::interrupt::registry().enable_interrupt_raw();
+    if let Some(entry) = entry {
+        entry();
+    }
+    exit()
+}
+
+/// Configures a kernel thread before starting it.
+pub struct Builder {
+    name: String,
+    policy: SchedPolicy,
+    cpu: Option<usize>,
+}
+
+impl Builder {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), policy: SchedPolicy::default(), cpu: None }
+    }
+
+    pub fn policy(mut self, policy: SchedPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Bind the thread to `cpu`.
+    pub fn cpu(mut self, cpu: usize) -> Self {
+        self.cpu = Some(cpu);
+        self
+    }
+
+    pub fn spawn<F>(self, f: F) -> SchedResult<Arc<Thread>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.policy.validate()?;
+        let thread = Thread::new(&self.name, false, self.cpu, Box::new(f));
+        thread.sched.lock().set_policy(self.policy);
+        scheduler::enqueue(thread.clone());
+        Ok(thread)
+    }
+}
+
+/// Start a kernel thread running `f`.
+pub fn spawn<F>(name: &str, f: F) -> Arc<Thread>
+where
+    F: FnOnce() + Send + 'static,
+{
+    Builder::new(name).spawn(f).expect("Default policy is valid")
+}
+
+/// Start a kernel thread running `f` bound to `cpu`.
+pub fn spawn_on<F>(cpu: usize, name: &str, f: F) -> Arc<Thread>
+where
+    F: FnOnce() + Send + 'static,
+{
+    Builder::new(name).cpu(cpu).spawn(f).expect("Default policy is valid")
+}
+
+/// The thread running on the current CPU.
+pub fn current() -> Arc<Thread> {
+    scheduler::current()
+}
+
+/// Give the CPU to the next runnable thread, if any.
+pub fn yield_now() {
+    scheduler::with_irq_disabled(|| {
+        current().sched.lock().yielding = true;
+        scheduler::schedule();
+    });
+}
+
+/// Block the current thread until `unpark` is called on it.
+///
+/// A wakeup issued before `park` is not lost, but `park` may also return
+/// spuriously, callers must recheck their condition.
+pub fn park() {
+    let thread = current();
+    if thread.unparked.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    scheduler::with_irq_disabled(|| {
+        thread.transition(ThreadState::Running, ThreadState::Blocked);
+        // An `unpark` between the check above and the transition either sees
+        // the thread running, or already queued it again
+        if thread.unparked.swap(false, Ordering::AcqRel)
+            && thread.transition(ThreadState::Blocked, ThreadState::Running) {
+            return;
+        }
+        scheduler::schedule();
+    });
+}
+
+/// Block the current thread for at least `duration`.
+pub fn sleep(duration: Duration) {
+    let deadline = clocksource::monotonic_ns().saturating_add(duration.as_nanos() as u64);
+    let thread = current();
+    let fired = Arc::new(AtomicBool::new(false));
+    let timer_fired = fired.clone();
+    let handle = timer::add_timer(deadline, move || {
+        timer_fired.store(true, Ordering::Release);
+        thread.unpark();
+    });
+    while !fired.load(Ordering::Acquire) {
+        park();
+    }
+    handle.cancel();
+}
+
+/// Terminate the current thread.
+pub fn exit() -> ! {
+    scheduler::with_irq_disabled(|| {
+        current().set_state(ThreadState::Dead);
+        scheduler::schedule();
+    });
+    unreachable!("Dead thread scheduled again");
+}
\ No newline at end of file
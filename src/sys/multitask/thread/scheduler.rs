@@ -0,0 +1,283 @@
+use crate::abstracts::context::ContextHAL;
+use crate::abstracts::interrupt::controller::InterruptController;
+use crate::sys;
+use crate::sys::interrupt::softirq;
+use crate::sys::multitask::context;
+use crate::sys::multitask::thread::class::RunQueue;
+use crate::sys::multitask::thread::{SchedPolicy, Thread, ThreadState};
+use crate::sys::smp::percpu;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::sync::rcu;
+use crate::sys::time::clocksource;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use spin::Once;
+
+struct CpuScheduler {
+    current: IrqSpinLock<Option<Arc<Thread>>>,
+    /// Thread switched away from, handed over to `finish_switch` on the next
+    /// thread.
+    prev: IrqSpinLock<Option<Arc<Thread>>>,
+    idle: Once<Arc<Thread>>,
+    need_resched: AtomicBool,
+    /// Time the running thread was last charged for its CPU time.
+    exec_start: AtomicU64,
+    /// Preemption is disabled while non-zero.
+    preempt_count: AtomicUsize,
+}
+
+impl CpuScheduler {
+    const fn new() -> Self {
+        Self {
+            current: IrqSpinLock::new(None),
+            prev: IrqSpinLock::new(None),
+            idle: Once::new(),
+            need_resched: AtomicBool::new(false),
+            exec_start: AtomicU64::new(0),
+            preempt_count: AtomicUsize::new(0),
+        }
+    }
+}
+
+static RUN_QUEUE: IrqSpinLock<RunQueue> = IrqSpinLock::new(RunQueue::new());
+crate::per_cpu! {
+    static CPUS: CpuScheduler = CpuScheduler::new();
+}
+
+fn this_cpu() -> &'static CpuScheduler {
+    CPUS.get()
+}
+
+/// Turn the running context of the current CPU into its boot thread and
+/// create its idle thread.
+pub fn init_cpu() {
+    let cpu = sys::smp::current_id();
+    let sched = CPUS.of(cpu);
+    let boot = Thread::bootstrap(&format!("boot/{}", cpu), cpu);
+    percpu::this().set_current_thread(&boot);
+    *sched.current.lock() = Some(boot);
+    sched.idle.call_once(|| {
+        let idle = Thread::new(&format!("idle/{}", cpu), true, Some(cpu), Box::new(idle_loop));
+        idle.sched.lock().set_policy(SchedPolicy::Idle);
+        idle
+    });
+    sched.exec_start.store(clocksource::monotonic_ns(), Ordering::Relaxed);
+}
+
+pub fn is_initialized() -> bool {
+    this_cpu().idle.is_completed()
+}
+
+pub(super) fn current() -> Arc<Thread> {
+    let thread = percpu::current_thread();
+    assert!(!thread.is_null(), "Scheduler not initialized on this CPU");
+    // The pointer is the calling thread itself, which `CpuScheduler::current`
+    // keeps alive while it runs
+    unsafe {
+        Arc::increment_strong_count(thread);
+        Arc::from_raw(thread)
+    }
+}
+
+/// Run `f` with interrupts disabled, restoring the previous state after.
+///
+/// The nesting counter of the interrupt controller is per CPU, and `f` may
+/// resume on another CPU, so interrupts are switched directly.
+pub(super) fn with_irq_disabled<R>(f: impl FnOnce() -> R) -> R {
+    let ic = sys::interrupt::registry();
+    let enabled = ic.is_interrupt_enabled();
+    let _ = ic.disable_interrupt_raw();
+    let result = f();
+    if enabled {
+        let _ = ic.enable_interrupt_raw();
+    }
+    result
+}
+
+/// Rank of the thread running on `cpu`, `None` if it is idle.
+fn current_rank(cpu: usize) -> Option<u32> {
+    let current = CPUS.of(cpu).current.lock();
+    current.as_ref().filter(|thread| !thread.idle).map(|thread| thread.policy().rank())
+}
+
+/// Charge the CPU time since the last update to the running thread.
+fn update_curr(sched: &CpuScheduler, thread: &Thread) {
+    let now = clocksource::monotonic_ns();
+    let delta = now.saturating_sub(sched.exec_start.swap(now, Ordering::Relaxed));
+    thread.sched.lock().account(delta);
+}
+
+/// Put a ready thread in the run queue, and preempt the CPU allowed to run
+/// it which runs the least important thread, if that one is outranked.
+pub(super) fn enqueue(thread: Arc<Thread>) {
+    let rank = thread.policy().rank();
+    let affinity = thread.affinity;
+    RUN_QUEUE.lock().enqueue(thread);
+
+    // An idle CPU has no rank and is picked first
+    let target = sys::smp::online_mask().iter()
+        .filter(|&cpu| affinity.map_or(true, |affinity| affinity == cpu))
+        .map(|cpu| (cpu, current_rank(cpu)))
+        .min_by_key(|&(_, current)| current)
+        .filter(|&(_, current)| current.map_or(true, |current| current < rank));
+    match target {
+        Some((cpu, _)) if cpu == sys::smp::current_id() => request_resched(),
+        Some((cpu, _)) => sys::smp::send_reschedule(cpu),
+        None => {}
+    }
+}
+
+/// Move a thread to `policy`, requeueing it if it is runnable.
+pub(super) fn set_policy(thread: &Arc<Thread>, policy: SchedPolicy) {
+    let queued = with_irq_disabled(|| {
+        let mut queue = RUN_QUEUE.lock();
+        let queued = queue.dequeue(thread);
+        thread.sched.lock().set_policy(policy);
+        queued
+    });
+    if queued {
+        enqueue(thread.clone());
+    } else if Arc::ptr_eq(thread, &current()) {
+        // Lowering the own priority may let a queued thread run
+        request_resched();
+    }
+}
+
+/// Switch to the next runnable thread. The current thread is queued again if
+/// it is still running, otherwise it is left parked or dead.
+///
+/// Must be called with interrupts disabled.
+pub(super) fn schedule() {
+    let cpu = sys::smp::current_id();
+    let sched = CPUS.of(cpu);
+    sched.need_resched.store(false, Ordering::Relaxed);
+    let prev = sched.current.lock().clone().expect("Scheduler not initialized on this CPU");
+    update_curr(sched, &prev);
+    rcu::note_quiescent_state();
+    let next = {
+        let mut queue = RUN_QUEUE.lock();
+        // Still runnable, it competes with the queued threads
+        if !prev.idle && prev.transition(ThreadState::Running, ThreadState::Ready) {
+            queue.enqueue(prev.clone());
+        }
+        queue.pick_next(cpu)
+    };
+    let next = next.unwrap_or_else(|| sched.idle.get().expect("Idle thread missing").clone());
+    next.sched.lock().start_slice();
+    if Arc::ptr_eq(&prev, &next) {
+        // Picked again, or woken up again before it could switch away
+        prev.set_state(ThreadState::Running);
+        return;
+    }
+
+    // The thread may still be switching away on the CPU which parked it
+    while next.on_cpu.load(Ordering::Acquire) {
+        core::hint::spin_loop();
+    }
+    next.set_state(ThreadState::Running);
+    next.on_cpu.store(true, Ordering::Relaxed);
+
+    let prev_context = prev.context.get();
+    let next_context = next.context.get();
+    let percpu = percpu::this();
+    percpu.set_current_thread(&next);
+    *sched.current.lock() = Some(next);
+    *sched.prev.lock() = Some(prev);
+    percpu.stats.context_switches.fetch_add(1, Ordering::Relaxed);
+    unsafe { context::switch(prev_context, next_context) };
+    finish_switch();
+}
+
+/// Complete a switch on the thread switched to, once the previous thread is
+/// no longer running on its stack. Another CPU may then run it.
+pub(super) fn finish_switch() {
+    let Some(prev) = this_cpu().prev.lock().take() else { return };
+    prev.on_cpu.store(false, Ordering::Release);
+}
+
+fn idle_loop() {
+    let ic = sys::interrupt::registry();
+    loop {
+        let _ = ic.disable_interrupt_raw();
+        let cpu = sys::smp::current_id();
+        if RUN_QUEUE.lock().highest_rank(cpu).is_none() {
+            sys::time::nohz::idle_enter();
+            // Entering idle may complete a grace period and raise its softirq
+            if !softirq::local_pending() {
+                // Enables interrupts and halts atomically, a wakeup arriving
+                // after the check above still ends the halt
+                ic.wait_for_interrupt();
+                let _ = ic.disable_interrupt_raw();
+            }
+            sys::time::nohz::idle_exit();
+        }
+        schedule();
+        let _ = ic.enable_interrupt_raw();
+        softirq::run_pending();
+    }
+}
+
+/// Account a tick to the running thread, called from the clock event
+/// interrupt. Its class decides whether the slice is used up.
+pub fn tick() {
+    if !is_initialized() {
+        return;
+    }
+    let cpu = sys::smp::current_id();
+    let sched = CPUS.of(cpu);
+    let Some(current) = sched.current.lock().clone() else { return };
+    update_curr(sched, &current);
+    if current.idle {
+        return;
+    }
+    let queue = RUN_QUEUE.lock();
+    if queue.task_tick(cpu, &mut current.sched.lock()) {
+        sched.need_resched.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Switch threads on the next interrupt exit, e.g. after a reschedule IPI
+/// announced a thread bound to this CPU.
+pub fn request_resched() {
+    this_cpu().need_resched.store(true, Ordering::Relaxed);
+}
+
+/// Preempt the running thread if its slice is used up or a more important
+/// thread became runnable. Called on return from an interrupt, with
+/// interrupts disabled.
+///
+/// `interrupted_irq_enabled` is the interrupt flag of the interrupted
+/// context. Code running with interrupts disabled, softirqs and threads
+/// inside `preempt_disable` are never preempted. Neither is a halted idle
+/// CPU, which switches once it restarted its tick, see
+/// [`preempt_if_requested`].
+pub fn preempt_on_irq_exit(interrupted_irq_enabled: bool) {
+    if interrupted_irq_enabled && !sys::time::nohz::is_idle(sys::smp::current_id()) {
+        preempt_if_requested();
+    }
+}
+
+/// Switch threads if an interrupt asked for it and the current thread may be
+/// preempted. Must be called with interrupts disabled.
+pub fn preempt_if_requested() {
+    let sched = this_cpu();
+    if sched.need_resched.load(Ordering::Relaxed)
+        && sched.preempt_count.load(Ordering::Relaxed) == 0
+        && !softirq::in_softirq()
+        && is_initialized()
+    {
+        schedule();
+    }
+}
+
+/// Prevent the current thread from being preempted until the matching
+/// `preempt_enable`.
+pub fn preempt_disable() {
+    with_irq_disabled(|| this_cpu().preempt_count.fetch_add(1, Ordering::Relaxed));
+}
+
+pub fn preempt_enable() {
+    with_irq_disabled(|| this_cpu().preempt_count.fetch_sub(1, Ordering::Relaxed));
+}
\ No newline at end of file
@@ -0,0 +1,353 @@
+use crate::sys;
+use crate::sys::multitask::thread::{self, Thread};
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::sync::notify::Notify;
+use crate::sys::time::clocksource;
+use crate::sys::time::timer::{self, TimerHandle};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use spin::Once;
+
+/// A job run by a workqueue. It may be queued again once it started running.
+pub struct Work {
+    func: Box<dyn Fn() + Send + Sync>,
+    /// Set from queueing until a worker starts running it.
+    pending: AtomicBool,
+}
+
+impl Work {
+    pub fn new<F>(func: F) -> Arc<Self>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Arc::new(Self { func: Box::new(func), pending: AtomicBool::new(false) })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+/// A work item queued once its delay has expired.
+pub struct DelayedWork {
+    work: Arc<Work>,
+    timer: IrqSpinLock<Option<TimerHandle>>,
+}
+
+impl DelayedWork {
+    pub fn new<F>(func: F) -> Arc<Self>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Arc::new(Self { work: Work::new(func), timer: IrqSpinLock::new(None) })
+    }
+
+    pub fn work(&self) -> &Arc<Work> {
+        &self.work
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.work.is_pending()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkQueueKind {
+    /// A worker bound to each CPU, work runs on the CPU which queued it.
+    PerCpu,
+    /// Up to `max_active` workers running on any CPU.
+    Unbound { max_active: usize },
+}
+
+struct PoolState {
+    /// Queued work with its ticket, in queueing order.
+    queue: VecDeque<(u64, Arc<Work>)>,
+    /// Tickets of the work being run.
+    running: Vec<u64>,
+    next_ticket: u64,
+    stopping: bool,
+}
+
+impl PoolState {
+    /// Whether all work queued before `ticket` was handed out has finished.
+    fn flushed(&self, ticket: u64) -> bool {
+        let queued = self.queue.front().map(|(ticket, _)| *ticket);
+        let oldest = self.running.iter().copied().chain(queued).min();
+        oldest.map_or(true, |oldest| oldest >= ticket)
+    }
+}
+
+/// Work items of one CPU of a per-CPU queue, or of an unbound queue.
+struct Pool {
+    state: IrqSpinLock<PoolState>,
+    workers: Once<Vec<Arc<Thread>>>,
+    /// Threads waiting in `flush`.
+    flushers: IrqSpinLock<Vec<Arc<Thread>>>,
+    /// Notified whenever a work item finishes, for `flush_async`.
+    finished: Notify,
+}
+
+impl Pool {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            state: IrqSpinLock::new(PoolState {
+                queue: VecDeque::new(),
+                running: Vec::new(),
+                next_ticket: 0,
+                stopping: false,
+            }),
+            workers: Once::new(),
+            flushers: IrqSpinLock::new(Vec::new()),
+            finished: Notify::new(),
+        })
+    }
+
+    fn wake_workers(&self) {
+        for worker in self.workers.get().into_iter().flatten() {
+            worker.unpark();
+        }
+    }
+
+    fn push(&self, work: Arc<Work>) {
+        {
+            let mut state = self.state.lock();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.queue.push_back((ticket, work));
+        }
+        self.wake_workers();
+    }
+
+    /// Remove `work` if it is queued here, it is no longer pending then.
+    fn remove(&self, work: &Arc<Work>) -> bool {
+        let mut state = self.state.lock();
+        let Some(index) = state.queue.iter().position(|(_, queued)| Arc::ptr_eq(queued, work)) else {
+            return false;
+        };
+        state.queue.remove(index);
+        work.pending.store(false, Ordering::Release);
+        true
+    }
+
+    fn worker_loop(&self) {
+        loop {
+            let next = {
+                let mut state = self.state.lock();
+                match state.queue.pop_front() {
+                    Some((ticket, work)) => {
+                        state.running.push(ticket);
+                        Some((ticket, work))
+                    }
+                    None if state.stopping => return,
+                    None => None,
+                }
+            };
+            let Some((ticket, work)) = next else {
+                thread::park();
+                continue;
+            };
+
+            // Cleared first, the work may queue itself again
+            work.pending.store(false, Ordering::Release);
+            (work.func)();
+
+            self.state.lock().running.retain(|running| *running != ticket);
+            for flusher in self.flushers.lock().drain(..) {
+                flusher.unpark();
+            }
+            self.finished.notify_waiters();
+        }
+    }
+
+    fn next_ticket(&self) -> u64 {
+        self.state.lock().next_ticket
+    }
+
+    fn flush(&self) {
+        let ticket = self.next_ticket();
+        let current = thread::current();
+        loop {
+            self.flushers.lock().push(current.clone());
+            // A worker finishing between the check and `park` unparks us
+            if self.state.lock().flushed(ticket) {
+                return;
+            }
+            thread::park();
+        }
+    }
+
+    async fn flush_async(&self) {
+        let ticket = self.next_ticket();
+        loop {
+            let finished = self.finished.notified();
+            if self.state.lock().flushed(ticket) {
+                return;
+            }
+            finished.await;
+        }
+    }
+}
+
+/// A named queue of work items run by kernel threads, which may sleep.
+pub struct WorkQueue {
+    name: String,
+    kind: WorkQueueKind,
+    /// A pool for each CPU, or a single one if unbound.
+    pools: Vec<Arc<Pool>>,
+}
+
+impl WorkQueue {
+    /// Create a queue and start its workers.
+    pub fn new(name: &str, kind: WorkQueueKind) -> Arc<Self> {
+        let pools: Vec<Arc<Pool>> = match kind {
+            WorkQueueKind::PerCpu => sys::smp::cpus().map(|_| Pool::new()).collect(),
+            WorkQueueKind::Unbound { .. } => Vec::from([Pool::new()]),
+        };
+        match kind {
+            WorkQueueKind::PerCpu => {
+                for (cpu, pool) in pools.iter().enumerate() {
+                    let worker_pool = pool.clone();
+                    let worker = thread::spawn_on(cpu, &format!("{}/{}", name, cpu), move || worker_pool.worker_loop());
+                    pool.workers.call_once(|| Vec::from([worker]));
+                }
+            }
+            WorkQueueKind::Unbound { max_active } => {
+                let pool = &pools[0];
+                let workers = (0..max_active.max(1)).map(|index| {
+                    let worker_pool = pool.clone();
+                    thread::spawn(&format!("{}/u{}", name, index), move || worker_pool.worker_loop())
+                }).collect();
+                pool.workers.call_once(|| workers);
+            }
+        }
+        Arc::new(Self { name: name.to_string(), kind, pools })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> WorkQueueKind {
+        self.kind
+    }
+
+    fn pool_of(&self, cpu: usize) -> &Arc<Pool> {
+        match self.kind {
+            WorkQueueKind::PerCpu => &self.pools[cpu],
+            WorkQueueKind::Unbound { .. } => &self.pools[0],
+        }
+    }
+
+    /// Queue `work` on the current CPU, returns `false` if it is already
+    /// pending. Safe to call from interrupt context.
+    pub fn queue_work(&self, work: &Arc<Work>) -> bool {
+        self.queue_work_on(sys::smp::current_id(), work)
+    }
+
+    /// Queue `work` on `cpu`, an unbound queue runs it on any CPU.
+    pub fn queue_work_on(&self, cpu: usize, work: &Arc<Work>) -> bool {
+        if work.pending.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        self.pool_of(cpu).push(work.clone());
+        true
+    }
+
+    /// Queue `work` once `delay` has passed, returns `false` if it is already
+    /// pending.
+    pub fn queue_delayed_work(self: &Arc<Self>, work: &Arc<DelayedWork>, delay: Duration) -> bool {
+        if work.work.pending.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let deadline = clocksource::monotonic_ns().saturating_add(delay.as_nanos() as u64);
+        let queue = self.clone();
+        let delayed = work.clone();
+        // Armed with the lock held, which keeps the timer softirq of this CPU
+        // from running the callback before the handle is stored
+        let mut handle = work.timer.lock();
+        // The timer fires on the current CPU, so per-CPU work stays here
+        *handle = Some(timer::add_timer(deadline, move || {
+            delayed.timer.lock().take();
+            queue.pool_of(sys::smp::current_id()).push(delayed.work.clone());
+        }));
+        true
+    }
+
+    /// Remove `work` from the queue if it is pending, returns whether it
+    /// was. A running execution is not interrupted.
+    pub fn cancel_work(&self, work: &Arc<Work>) -> bool {
+        self.pools.iter().any(|pool| pool.remove(work))
+    }
+
+    /// Stop the timer of `work` or remove it from the queue, returns whether
+    /// it was pending. Once the timer fired, its callback queues the work, so
+    /// the work may still run if it was not queued yet.
+    pub fn cancel_delayed_work(&self, work: &Arc<DelayedWork>) -> bool {
+        let timer = work.timer.lock().take();
+        // Only a cancellation winning against the timer keeps it from queueing
+        if timer.is_some_and(|timer| timer.cancel()) {
+            work.work.pending.store(false, Ordering::Release);
+            return true;
+        }
+        self.cancel_work(&work.work)
+    }
+
+    /// Block the current thread until all work queued before has finished.
+    /// Executor tasks use [`flush_async`](Self::flush_async) instead.
+    pub fn flush(&self) {
+        for pool in self.pools.iter() {
+            pool.flush();
+        }
+    }
+
+    /// Wait until all work queued before has finished.
+    pub async fn flush_async(&self) {
+        for pool in self.pools.iter() {
+            pool.flush_async().await;
+        }
+    }
+
+    /// Run the queued work, then stop the workers. Work queued afterwards
+    /// never runs.
+    pub fn destroy(&self) {
+        self.flush();
+        for pool in self.pools.iter() {
+            pool.state.lock().stopping = true;
+            pool.wake_workers();
+        }
+    }
+}
+
+static SYSTEM_WQ: Once<Arc<WorkQueue>> = Once::new();
+static SYSTEM_UNBOUND_WQ: Once<Arc<WorkQueue>> = Once::new();
+
+/// Per-CPU queue for short jobs of any subsystem.
+pub fn system_wq() -> &'static Arc<WorkQueue> {
+    SYSTEM_WQ.get().expect("Workqueues not initialized")
+}
+
+/// Unbound queue for long running jobs.
+pub fn system_unbound_wq() -> &'static Arc<WorkQueue> {
+    SYSTEM_UNBOUND_WQ.get().expect("Workqueues not initialized")
+}
+
+/// Queue `work` on the system queue.
+pub fn schedule_work(work: &Arc<Work>) -> bool {
+    system_wq().queue_work(work)
+}
+
+/// Queue `work` on the system queue once `delay` has passed.
+pub fn schedule_delayed_work(work: &Arc<DelayedWork>, delay: Duration) -> bool {
+    system_wq().queue_delayed_work(work, delay)
+}
+
+pub fn init() {
+    SYSTEM_WQ.call_once(|| WorkQueue::new("events", WorkQueueKind::PerCpu));
+    SYSTEM_UNBOUND_WQ.call_once(|| WorkQueue::new("events_unbound", WorkQueueKind::Unbound { max_active: sys::smp::cpu_count() }));
+}
\ No newline at end of file
@@ -0,0 +1,98 @@
+use crate::abstracts::interrupt::ipi::{IpiHAL, IpiKind, IpiTarget};
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::smp::CpuMask;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_queue::SegQueue;
+use log::warn;
+
+struct CallRequest {
+    func: Box<dyn Fn() + Send + Sync>,
+    /// Number of CPUs which have not finished running `func` yet.
+    pending: AtomicUsize,
+}
+
+static CALL_QUEUES: [SegQueue<Arc<CallRequest>>; KERNEL_MAX_CPU_NUM] = [const { SegQueue::new() }; KERNEL_MAX_CPU_NUM];
+
+/// Run `func` on the CPU `cpu`.
+///
+/// If `wait` is set, this returns only after `func` has finished, so it must
+/// not be called with interrupts disabled or from an interrupt handler.
+pub fn call_on_cpu<F>(cpu: usize, func: F, wait: bool)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    call_on_mask(CpuMask::single(cpu), func, wait)
+}
+
+/// Run `func` on every online CPU, including the current one.
+pub fn call_on_each_cpu<F>(func: F, wait: bool)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    call_on_mask(sys::smp::online_mask(), func, wait)
+}
+
+/// Run `func` on every online CPU except the current one.
+pub fn call_on_other_cpus<F>(func: F, wait: bool)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let mut mask = sys::smp::online_mask();
+    mask.clear(sys::smp::current_id());
+    call_on_mask(mask, func, wait)
+}
+
+/// Run `func` on every CPU in `mask`.
+///
+/// The current CPU, if present in `mask`, runs `func` directly. Offline CPUs
+/// are skipped.
+pub fn call_on_mask<F>(mask: CpuMask, func: F, wait: bool)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let current = sys::smp::current_id();
+    let online = sys::smp::online_mask();
+    if mask.bits() & !online.bits() != 0 {
+        warn!("Skipping cross-CPU call on offline CPUs: {:#x}", mask.bits() & !online.bits());
+    }
+
+    let mut remote = CpuMask::from_bits(mask.bits() & online.bits());
+    remote.clear(current);
+
+    let request = Arc::new(CallRequest {
+        func: Box::new(func),
+        pending: AtomicUsize::new(remote.count()),
+    });
+    if !remote.is_empty() {
+        for cpu in remote.iter() {
+            CALL_QUEUES[cpu].push(request.clone());
+        }
+        sys::interrupt::ipi::send_ipi(
+            IpiTarget::Mask(remote),
+            IpiKind::Fixed(sys::interrupt::ipi::CALL_FUNCTION_VECTOR),
+        );
+    }
+
+    if mask.contains(current) {
+        (request.func)();
+    }
+
+    if wait {
+        while request.pending.load(Ordering::Acquire) != 0 {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Handler of the call function IPI, runs all requests queued for the
+/// current CPU.
+pub fn handle_ipi() {
+    let queue = &CALL_QUEUES[sys::smp::current_id()];
+    while let Some(request) = queue.pop() {
+        (request.func)();
+        request.pending.fetch_sub(1, Ordering::Release);
+    }
+}
\ No newline at end of file
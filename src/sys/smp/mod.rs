@@ -0,0 +1,134 @@
+use crate::abstracts::cpu::CpuHAL;
+use crate::abstracts::interrupt::ipi::{IpiHAL, IpiKind, IpiTarget};
+use crate::boot::BOOTINFO;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+use log::info;
+
+pub mod call;
+pub mod percpu;
+pub mod stop;
+
+pub use crate::arch::hal_impl::cpu::CpuHALImpl as cpu;
+
+lazy_static!(
+    /// Local APIC IDs of all processors reported by the bootloader, indexed by
+    /// logical CPU id. The BSP always owns logical id 0.
+    static ref CPU_APIC_IDS: Vec<u32> = {
+        let smp = BOOTINFO.smp_response;
+        let bsp_lapic_id = smp.bsp_lapic_id();
+        let mut ids = Vec::with_capacity(smp.cpus().len());
+        ids.push(bsp_lapic_id);
+        ids.extend(smp.cpus().iter().map(|cpu| cpu.lapic_id).filter(|id| *id != bsp_lapic_id));
+        assert!(ids.len() <= KERNEL_MAX_CPU_NUM, "Too many CPUs: {} (max {})", ids.len(), KERNEL_MAX_CPU_NUM);
+        ids
+    };
+);
+
+static ONLINE_CPUS: AtomicU64 = AtomicU64::new(0);
+
+/// A set of logical CPU ids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuMask(u64);
+
+impl CpuMask {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub fn single(cpu: usize) -> Self {
+        let mut mask = Self::empty();
+        mask.set(cpu);
+        mask
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn set(&mut self, cpu: usize) {
+        assert!(cpu < KERNEL_MAX_CPU_NUM, "Invalid CPU id: {}", cpu);
+        self.0 |= 1 << cpu;
+    }
+
+    pub fn clear(&mut self, cpu: usize) {
+        assert!(cpu < KERNEL_MAX_CPU_NUM, "Invalid CPU id: {}", cpu);
+        self.0 &= !(1 << cpu);
+    }
+
+    pub fn contains(&self, cpu: usize) -> bool {
+        cpu < KERNEL_MAX_CPU_NUM && self.0 & (1 << cpu) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=usize> {
+        let bits = self.0;
+        (0..KERNEL_MAX_CPU_NUM).filter(move |cpu| bits & (1 << cpu) != 0)
+    }
+}
+
+/// Number of processors reported by the bootloader.
+pub fn cpu_count() -> usize {
+    CPU_APIC_IDS.len()
+}
+
+/// Logical ids of all processors reported by the bootloader.
+pub fn cpus() -> Range<usize> {
+    0..cpu_count()
+}
+
+/// Local APIC ID of the logical CPU `cpu`.
+pub fn apic_id(cpu: usize) -> u32 {
+    CPU_APIC_IDS[cpu]
+}
+
+/// Logical id of the CPU owning the local APIC `apic_id`.
+pub fn cpu_of_apic(apic_id: u32) -> Option<usize> {
+    CPU_APIC_IDS.iter().position(|id| *id == apic_id)
+}
+
+/// Logical id of the current CPU.
+pub fn current_id() -> usize {
+    cpu::cpu_id()
+}
+
+/// Mark the current CPU as ready to receive inter-processor interrupts.
+pub fn set_online() {
+    ONLINE_CPUS.fetch_or(1 << current_id(), Ordering::Release);
+}
+
+/// Interrupt `cpu` so it looks for new work, waking it up if it is halted.
+pub fn send_reschedule(cpu: usize) {
+    sys::interrupt::ipi::send_ipi(IpiTarget::Cpu(cpu), IpiKind::Fixed(sys::interrupt::ipi::RESCHEDULE_VECTOR));
+}
+
+pub fn online_mask() -> CpuMask {
+    CpuMask::from_bits(ONLINE_CPUS.load(Ordering::Acquire))
+}
+
+pub fn is_online(cpu: usize) -> bool {
+    online_mask().contains(cpu)
+}
+
+pub fn module_init() {
+    info!("☞ Hikari SMP Module");
+    for cpu in cpus() {
+        info!("  - CPU {}: Local APIC ID {}", cpu, apic_id(cpu));
+    }
+    set_online();
+}
\ No newline at end of file
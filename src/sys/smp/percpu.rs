@@ -0,0 +1,174 @@
+use crate::abstracts::cpu::CpuHAL;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys::multitask::thread::Thread;
+use crate::sys::multitask::TaskQueue;
+use crate::sys::smp::cpu;
+use alloc::sync::Arc;
+use core::mem::offset_of;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+/// Declare per-CPU variables, holding one value for each CPU.
+///
+/// Each variable is a static array indexed by the CPU id read from the
+/// [`PerCpu`] block, not storage relative to the base register.
+///
+/// ```ignore
+/// per_cpu! {
+///     static TICKS: AtomicU64 = AtomicU64::new(0);
+/// }
+///
+/// TICKS.get().fetch_add(1, Ordering::Relaxed);
+/// let ticks_of_cpu1 = TICKS.of(1).load(Ordering::Relaxed);
+/// ```
+#[macro_export]
+macro_rules! per_cpu {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::sys::smp::percpu::PerCpuVar<$ty> =
+                $crate::sys::smp::percpu::PerCpuVar::new([const { $init }; $crate::kinfo::KERNEL_MAX_CPU_NUM]);
+        )*
+    };
+}
+
+/// A variable with one value for each CPU, see [`per_cpu!`].
+pub struct PerCpuVar<T>([T; KERNEL_MAX_CPU_NUM]);
+
+impl<T> PerCpuVar<T> {
+    pub const fn new(values: [T; KERNEL_MAX_CPU_NUM]) -> Self {
+        Self(values)
+    }
+
+    /// Value of the current CPU. The caller must not migrate to another CPU
+    /// while using it, or only use it for statistics.
+    pub fn get(&self) -> &T {
+        &self.0[cpu::cpu_id()]
+    }
+
+    /// Value of `cpu`.
+    pub fn of(&self, cpu: usize) -> &T {
+        &self.0[cpu]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.0.iter()
+    }
+}
+
+/// Counters of a CPU.
+#[derive(Debug, Default)]
+pub struct CpuStats {
+    /// Hardware interrupts handled.
+    pub interrupts: AtomicU64,
+    /// Switches between kernel threads.
+    pub context_switches: AtomicU64,
+}
+
+/// Data of a CPU, reached through its per-CPU base register.
+///
+/// The hot fields are read with a single load relative to the base register,
+/// see [`current_thread`] and [`run_queue`].
+#[repr(C)]
+pub struct PerCpu {
+    /// Address of the block itself, loaded through the base register.
+    self_ptr: AtomicUsize,
+    cpu_id: AtomicUsize,
+    /// Thread running on the CPU. The scheduler holds the reference keeping
+    /// it alive, null until it is initialized on the CPU.
+    current_thread: AtomicPtr<Thread>,
+    /// Ready tasks of the executor running on the CPU, null until one runs.
+    run_queue: AtomicPtr<TaskQueue>,
+    /// Depth of nested hardware interrupt handlers.
+    irq_depth: AtomicUsize,
+    pub stats: CpuStats,
+}
+
+impl PerCpu {
+    pub const SELF_PTR_OFFSET: usize = offset_of!(PerCpu, self_ptr);
+    pub const CPU_ID_OFFSET: usize = offset_of!(PerCpu, cpu_id);
+    pub const CURRENT_THREAD_OFFSET: usize = offset_of!(PerCpu, current_thread);
+    pub const RUN_QUEUE_OFFSET: usize = offset_of!(PerCpu, run_queue);
+
+    const fn new() -> Self {
+        Self {
+            self_ptr: AtomicUsize::new(0),
+            cpu_id: AtomicUsize::new(0),
+            current_thread: AtomicPtr::new(ptr::null_mut()),
+            run_queue: AtomicPtr::new(ptr::null_mut()),
+            irq_depth: AtomicUsize::new(0),
+            stats: CpuStats {
+                interrupts: AtomicU64::new(0),
+                context_switches: AtomicU64::new(0),
+            },
+        }
+    }
+
+    pub fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Relaxed)
+    }
+
+    /// Make `thread` the one running on this CPU. The caller keeps it alive
+    /// until another one replaces it.
+    pub fn set_current_thread(&self, thread: &Arc<Thread>) {
+        self.current_thread.store(Arc::as_ptr(thread) as *mut Thread, Ordering::Relaxed);
+    }
+
+    pub fn set_run_queue(&self, queue: &'static TaskQueue) {
+        self.run_queue.store(queue as *const TaskQueue as *mut TaskQueue, Ordering::Release);
+    }
+
+    /// Called when a hardware interrupt handler starts.
+    pub fn enter_irq(&self) {
+        self.irq_depth.fetch_add(1, Ordering::Relaxed);
+        self.stats.interrupts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Called when a hardware interrupt handler returns.
+    pub fn leave_irq(&self) {
+        self.irq_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn irq_depth(&self) -> usize {
+        self.irq_depth.load(Ordering::Relaxed)
+    }
+}
+
+static PER_CPU: [PerCpu; KERNEL_MAX_CPU_NUM] = [const { PerCpu::new() }; KERNEL_MAX_CPU_NUM];
+
+/// Set up the per-CPU data of the current CPU, which is the logical CPU `cpu`.
+///
+/// Must run on each CPU before anything asks for its id.
+pub fn init(cpu: usize) {
+    assert!(cpu < KERNEL_MAX_CPU_NUM, "Invalid CPU id: {}", cpu);
+    let data = &PER_CPU[cpu];
+    data.self_ptr.store(data as *const PerCpu as usize, Ordering::Relaxed);
+    data.cpu_id.store(cpu, Ordering::Relaxed);
+    unsafe { cpu::set_local_base(data as *const PerCpu as usize) };
+}
+
+/// Data of the current CPU.
+pub fn this() -> &'static PerCpu {
+    unsafe { &*(cpu::local_base() as *const PerCpu) }
+}
+
+/// Thread running on the current CPU, null before the scheduler is
+/// initialized on it.
+pub fn current_thread() -> *const Thread {
+    cpu::local_read(PerCpu::CURRENT_THREAD_OFFSET) as *const Thread
+}
+
+/// Run queue of the executor of the current CPU.
+pub fn run_queue() -> Option<&'static TaskQueue> {
+    unsafe { (cpu::local_read(PerCpu::RUN_QUEUE_OFFSET) as *const TaskQueue).as_ref() }
+}
+
+/// Data of `cpu`.
+pub fn of(cpu: usize) -> &'static PerCpu {
+    &PER_CPU[cpu]
+}
+
+/// Whether the current CPU runs a hardware interrupt handler.
+pub fn in_interrupt() -> bool {
+    this().irq_depth() != 0
+}
\ No newline at end of file
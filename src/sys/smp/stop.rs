@@ -0,0 +1,64 @@
+use crate::abstracts::interrupt::ipi::{IpiHAL, IpiKind, IpiTarget};
+use crate::devices::pit;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::smp::CpuMask;
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Once;
+use trapframe::TrapFrame;
+
+/// How long the stopping CPU waits for the others to answer the NMI.
+const STOP_TIMEOUT_US: u64 = 1_000_000;
+const STOP_POLL_US: u64 = 1_000;
+
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static STOPPED_CPUS: AtomicU64 = AtomicU64::new(0);
+/// Register state of each stopped CPU when the NMI arrived.
+static STOPPED_FRAMES: [Once<TrapFrame>; KERNEL_MAX_CPU_NUM] = [const { Once::new() }; KERNEL_MAX_CPU_NUM];
+
+/// Halt every other online CPU with an NMI.
+///
+/// Returns the CPUs which stopped before the timeout, their register state
+/// is available through [`stopped_frame`].
+pub fn stop_other_cpus() -> CpuMask {
+    let mut others = sys::smp::online_mask();
+    others.clear(sys::smp::current_id());
+    if others.is_empty() || STOPPING.swap(true, Ordering::AcqRel) {
+        return stopped_mask();
+    }
+
+    sys::interrupt::ipi::send_ipi(IpiTarget::Mask(others), IpiKind::Nmi);
+    for _ in 0..STOP_TIMEOUT_US / STOP_POLL_US {
+        if stopped_mask().bits() & others.bits() == others.bits() {
+            break;
+        }
+        pit::busy_wait_us(STOP_POLL_US);
+    }
+    stopped_mask()
+}
+
+pub fn stopped_mask() -> CpuMask {
+    CpuMask::from_bits(STOPPED_CPUS.load(Ordering::Acquire))
+}
+
+pub fn stopped_frame(cpu: usize) -> Option<&'static TrapFrame> {
+    STOPPED_FRAMES[cpu].get()
+}
+
+/// Called by the NMI handler, parks the current CPU forever if a stop was
+/// requested. Returns `false` if the NMI was not sent by [`stop_other_cpus`].
+pub fn handle_nmi(tf: &TrapFrame) -> bool {
+    if !STOPPING.load(Ordering::Acquire) {
+        return false;
+    }
+
+    let cpu = sys::smp::current_id();
+    STOPPED_FRAMES[cpu].call_once(|| tf.clone());
+    STOPPED_CPUS.fetch_or(1 << cpu, Ordering::Release);
+    loop {
+        unsafe {
+            asm!("cli; hlt");
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,75 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::Waker;
+
+const WAITING: usize = 0;
+const REGISTERING: usize = 1 << 0;
+const WAKING: usize = 1 << 1;
+
+/// A slot holding the waker of a single task, which can be woken from any
+/// context including interrupt handlers.
+///
+/// Neither `register` nor `wake` ever blocks: a `wake` racing with a
+/// concurrent `register` is handed over to the registering side.
+pub struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
+
+impl AtomicWaker {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(WAITING),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Register `waker` to be woken by the next `wake`.
+    pub fn register(&self, waker: &Waker) {
+        match self.state.compare_exchange(WAITING, REGISTERING, Ordering::Acquire, Ordering::Acquire) {
+            Ok(_) => unsafe {
+                match &*self.waker.get() {
+                    Some(old) if old.will_wake(waker) => {}
+                    _ => *self.waker.get() = Some(waker.clone()),
+                }
+                if self.state.compare_exchange(REGISTERING, WAITING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                    // A wake arrived while registering, deliver it now
+                    let waker = (*self.waker.get()).take();
+                    self.state.swap(WAITING, Ordering::AcqRel);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            },
+            Err(WAKING) => {
+                // A wake is in progress, make sure the task gets polled again
+                waker.wake_by_ref();
+            }
+            Err(_) => {
+                // Concurrent register calls, only one of them wins
+            }
+        }
+    }
+
+    /// Wake the registered waker, if any.
+    pub fn wake(&self) {
+        if let Some(waker) = self.take() {
+            waker.wake();
+        }
+    }
+
+    /// Take the registered waker out of the slot.
+    pub fn take(&self) -> Option<Waker> {
+        match self.state.fetch_or(WAKING, Ordering::AcqRel) {
+            WAITING => {
+                let waker = unsafe { (*self.waker.get()).take() };
+                self.state.fetch_and(!WAKING, Ordering::Release);
+                waker
+            }
+            _ => None,
+        }
+    }
+}
\ No newline at end of file
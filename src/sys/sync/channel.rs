@@ -0,0 +1,161 @@
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::sync::wait_queue::WaitQueue;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Every receiver was dropped, the value is handed back.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    /// Every sender was dropped and the channel is drained.
+    Closed,
+}
+
+struct Inner<T> {
+    queue: IrqSpinLock<VecDeque<T>>,
+    capacity: usize,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    /// Senders waiting for room.
+    send_waiters: WaitQueue,
+    /// Receivers waiting for a value.
+    recv_waiters: WaitQueue,
+}
+
+impl<T> Inner<T> {
+    fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        if self.receivers.load(Ordering::Acquire) == 0 {
+            return Err(TrySendError::Closed(value));
+        }
+        let mut queue = self.queue.lock();
+        if queue.len() >= self.capacity {
+            return Err(TrySendError::Full(value));
+        }
+        queue.push_back(value);
+        Ok(())
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        if let Some(value) = self.queue.lock().pop_front() {
+            return Ok(value);
+        }
+        match self.senders.load(Ordering::Acquire) {
+            0 => Err(TryRecvError::Closed),
+            _ => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+/// Sending half of a bounded multi-producer multi-consumer channel.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Receiving half of a bounded multi-producer multi-consumer channel.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Create a channel buffering up to `capacity` values.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "Channel capacity must not be zero");
+    let inner = Arc::new(Inner {
+        queue: IrqSpinLock::new(VecDeque::with_capacity(capacity)),
+        capacity,
+        senders: AtomicUsize::new(1),
+        receivers: AtomicUsize::new(1),
+        send_waiters: WaitQueue::new(),
+        recv_waiters: WaitQueue::new(),
+    });
+    (Sender { inner: inner.clone() }, Receiver { inner })
+}
+
+impl<T> Sender<T> {
+    /// Send `value`, waiting for room in the channel.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+        let result = self.inner.send_waiters.wait_until(|| {
+            match self.inner.try_send(value.take().unwrap()) {
+                Ok(()) => Some(Ok(())),
+                Err(TrySendError::Closed(value)) => Some(Err(SendError(value))),
+                Err(TrySendError::Full(full)) => {
+                    value = Some(full);
+                    None
+                }
+            }
+        }).await;
+        if result.is_ok() {
+            self.inner.recv_waiters.wake_one();
+        }
+        result
+    }
+
+    /// Send `value` without waiting, safe to call from interrupt context.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.inner.try_send(value)?;
+        self.inner.recv_waiters.wake_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.inner.senders.fetch_add(1, Ordering::Relaxed);
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.inner.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.inner.recv_waiters.wake_all();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receive a value, waiting until one is sent. Resolves to `None` once
+    /// every sender was dropped and the channel is drained.
+    pub async fn recv(&self) -> Option<T> {
+        let value = self.inner.recv_waiters.wait_until(|| match self.inner.try_recv() {
+            Ok(value) => Some(Some(value)),
+            Err(TryRecvError::Closed) => Some(None),
+            Err(TryRecvError::Empty) => None,
+        }).await;
+        if value.is_some() {
+            self.inner.send_waiters.wake_one();
+        }
+        value
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let value = self.inner.try_recv()?;
+        self.inner.send_waiters.wake_one();
+        Ok(value)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.inner.receivers.fetch_add(1, Ordering::Relaxed);
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if self.inner.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.inner.send_waiters.wake_all();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+use crate::sys;
+#[cfg(feature = "lockdep")]
+use crate::sys::sync::lockdep::{self, LockClass};
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use spin::{Mutex, MutexGuard};
+
+/// A spin lock which disables interrupts on the current CPU while held.
+///
+/// Data shared with interrupt handlers must use this instead of a plain
+/// `spin::Mutex`, otherwise a handler taking the lock on the CPU already
+/// holding it spins forever. Interrupts are disabled through the nesting
+/// counter of the interrupt controller, so the previous state is restored on
+/// unlock. Before any interrupt controller is registered, it behaves as a
+/// plain spin lock.
+pub struct IrqSpinLock<T: ?Sized> {
+    /// Place the lock was created at, locks created there share the class.
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
+    inner: Mutex<T>,
+}
+
+pub struct IrqSpinLockGuard<'a, T: ?Sized + 'a> {
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
+    guard: ManuallyDrop<MutexGuard<'a, T>>,
+    /// Whether interrupts were disabled by this guard.
+    irq_disabled: bool,
+}
+
+impl<T> IrqSpinLock<T> {
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            #[cfg(feature = "lockdep")]
+            class: core::panic::Location::caller(),
+            inner: Mutex::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> IrqSpinLock<T> {
+    pub fn lock(&self) -> IrqSpinLockGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        let irqs_enabled = sys::interrupt::get_ic().is_interrupt_enabled();
+        let irq_disabled = sys::interrupt::get_ic().disable_interrupt().is_ok();
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(self.class, false, irqs_enabled);
+        IrqSpinLockGuard {
+            #[cfg(feature = "lockdep")]
+            class: self.class,
+            guard: ManuallyDrop::new(self.inner.lock()),
+            irq_disabled,
+        }
+    }
+
+    pub fn try_lock(&self) -> Option<IrqSpinLockGuard<'_, T>> {
+        #[cfg(feature = "lockdep")]
+        let irqs_enabled = sys::interrupt::get_ic().is_interrupt_enabled();
+        let irq_disabled = sys::interrupt::get_ic().disable_interrupt().is_ok();
+        match self.inner.try_lock() {
+            Some(guard) => {
+                #[cfg(feature = "lockdep")]
+                lockdep::acquire(self.class, true, irqs_enabled);
+                Some(IrqSpinLockGuard {
+                    #[cfg(feature = "lockdep")]
+                    class: self.class,
+                    guard: ManuallyDrop::new(guard),
+                    irq_disabled,
+                })
+            }
+            None => {
+                if irq_disabled {
+                    let _ = sys::interrupt::get_ic().enable_interrupt();
+                }
+                None
+            }
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Release the lock without a guard.
+    ///
+    /// # Safety
+    ///
+    /// Only for recovery paths like panic, where the holder will never run
+    /// again. The interrupt state of the holder is not restored.
+    pub unsafe fn force_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.class);
+        self.inner.force_unlock()
+    }
+}
+
+impl<T: ?Sized> Deref for IrqSpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for IrqSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: ?Sized> Drop for IrqSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // Unlock before interrupts come back
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.class);
+        if self.irq_disabled {
+            let _ = sys::interrupt::get_ic().enable_interrupt();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,269 @@
+use crate::abstracts::interrupt::controller::InterruptController;
+use crate::common::debug::unwind::trace::stack_trace;
+use crate::sys;
+use crate::sys::smp::percpu;
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, Ordering};
+use log::error;
+use spin::Mutex;
+
+/// Lock classes tracked, the validator turns off once they run out.
+pub const MAX_LOCK_CLASSES: usize = 256;
+/// Locks a CPU may hold at once.
+pub const MAX_HELD_LOCKS: usize = 32;
+
+pub type LockClass = &'static Location<'static>;
+
+/// Taken from an interrupt handler.
+const USED_IN_IRQ: u8 = 1 << 0;
+/// Held while interrupts were enabled.
+const HELD_WITH_IRQS_ENABLED: u8 = 1 << 1;
+
+struct Graph {
+    classes: [Option<LockClass>; MAX_LOCK_CLASSES],
+    count: usize,
+    /// `after[a]` has bit `b` set once class `b` was taken while `a` was held.
+    after: [[u64; MAX_LOCK_CLASSES / 64]; MAX_LOCK_CLASSES],
+    usage: [u8; MAX_LOCK_CLASSES],
+}
+
+impl Graph {
+    const fn new() -> Self {
+        Self {
+            classes: [None; MAX_LOCK_CLASSES],
+            count: 0,
+            after: [[0; MAX_LOCK_CLASSES / 64]; MAX_LOCK_CLASSES],
+            usage: [0; MAX_LOCK_CLASSES],
+        }
+    }
+
+    /// Index of `class`, registering it on first use.
+    fn index(&mut self, class: LockClass) -> Option<usize> {
+        if let Some(index) = self.classes[..self.count].iter().position(|known| known.is_some_and(|known| ptr_eq(known, class))) {
+            return Some(index);
+        }
+        if self.count == MAX_LOCK_CLASSES {
+            return None;
+        }
+        self.classes[self.count] = Some(class);
+        self.count += 1;
+        Some(self.count - 1)
+    }
+
+    fn name(&self, index: usize) -> LockClass {
+        self.classes[index].expect("Unregistered lock class")
+    }
+
+    fn has_edge(&self, from: usize, to: usize) -> bool {
+        self.after[from][to / 64] & (1 << (to % 64)) != 0
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        self.after[from][to / 64] |= 1 << (to % 64);
+    }
+
+    /// Shortest chain of dependencies leading from `from` to `to`, written
+    /// into `path`. Returns its length.
+    fn find_path(&self, from: usize, to: usize, path: &mut [usize; MAX_LOCK_CLASSES]) -> Option<usize> {
+        let mut parent = [usize::MAX; MAX_LOCK_CLASSES];
+        let mut queue = [0usize; MAX_LOCK_CLASSES];
+        let (mut head, mut tail) = (0, 1);
+        queue[0] = from;
+        parent[from] = from;
+        while head < tail {
+            let class = queue[head];
+            head += 1;
+            if class == to {
+                let mut len = 0;
+                let mut node = to;
+                loop {
+                    path[len] = node;
+                    len += 1;
+                    if node == from {
+                        break;
+                    }
+                    node = parent[node];
+                }
+                path[..len].reverse();
+                return Some(len);
+            }
+            for next in 0..self.count {
+                if parent[next] == usize::MAX && self.has_edge(class, next) {
+                    parent[next] = class;
+                    queue[tail] = next;
+                    tail += 1;
+                }
+            }
+        }
+        None
+    }
+}
+
+struct HeldLocks {
+    classes: [usize; MAX_HELD_LOCKS],
+    len: usize,
+}
+
+static GRAPH: Mutex<Graph> = Mutex::new(Graph::new());
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+crate::per_cpu! {
+    /// Classes held by each CPU in acquisition order. `IrqSpinLock` keeps
+    /// interrupts disabled, so its holder can not migrate.
+    static HELD: Mutex<HeldLocks> = Mutex::new(HeldLocks { classes: [0; MAX_HELD_LOCKS], len: 0 });
+}
+
+fn ptr_eq(a: LockClass, b: LockClass) -> bool {
+    core::ptr::eq(a, b)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Turn the validator off, returns `false` if it already was. Locks taken
+/// while printing a report are then no longer checked.
+fn turn_off() -> bool {
+    ENABLED.swap(false, Ordering::AcqRel)
+}
+
+fn report_header(title: &str) {
+    error!("========================================================");
+    error!("LOCKDEP: {} on CPU {}", title, sys::smp::current_id());
+    error!("--------------------------------------------------------");
+}
+
+fn report_held(graph: &Graph, held: &HeldLocks) {
+    error!("Locks held:");
+    for (depth, &class) in held.classes[..held.len].iter().enumerate() {
+        error!("  #{}: {}", depth, graph.name(class));
+    }
+}
+
+fn report_footer() {
+    stack_trace();
+    error!("========================================================");
+}
+
+/// First of `classes` which is both taken in interrupt context and held
+/// with interrupts enabled, reported as IRQ-unsafe.
+fn irq_unsafe(graph: &Graph, mut classes: impl Iterator<Item=usize>) -> Option<usize> {
+    classes.find(|&class| graph.usage[class] & (USED_IN_IRQ | HELD_WITH_IRQS_ENABLED) == USED_IN_IRQ | HELD_WITH_IRQS_ENABLED)
+}
+
+fn report_irq_unsafe(graph: &Graph, held: &HeldLocks, class: usize) {
+    if turn_off() {
+        report_header("IRQ-unsafe lock usage");
+        error!("{} is taken in interrupt context but was held with interrupts enabled", graph.name(class));
+        report_held(graph, held);
+        report_footer();
+    }
+}
+
+/// Check and record the acquisition of a lock of `class`, called before
+/// spinning on it. A `try_lock` can not deadlock and is only recorded.
+///
+/// `irqs_enabled` is the interrupt state before the lock disabled them. If
+/// set, the locks held so far are held with interrupts enabled.
+pub fn acquire(class: LockClass, try_lock: bool, irqs_enabled: bool) {
+    if !is_enabled() {
+        return;
+    }
+    let in_irq = percpu::in_interrupt();
+    let mut graph = GRAPH.lock();
+    let mut held = HELD.get().lock();
+    let Some(new) = graph.index(class) else {
+        if turn_off() {
+            error!("LOCKDEP: Out of lock classes, validator turned off");
+        }
+        return;
+    };
+
+    for &prev in held.classes[..held.len].iter() {
+        if try_lock || graph.has_edge(prev, new) {
+            continue;
+        }
+        if prev == new {
+            if turn_off() {
+                report_header("Possible recursive locking");
+                error!("Taking {} again", graph.name(new));
+                report_held(&graph, &held);
+                report_footer();
+            }
+            return;
+        }
+        let mut path = [0; MAX_LOCK_CLASSES];
+        if let Some(len) = graph.find_path(new, prev, &mut path) {
+            if turn_off() {
+                report_header("Possible circular locking dependency");
+                error!("Taking {} while holding {}", graph.name(new), graph.name(prev));
+                error!("but the reverse order was recorded before:");
+                for &class in path[..len].iter() {
+                    error!("  -> {}", graph.name(class));
+                }
+                report_held(&graph, &held);
+                report_footer();
+            }
+            return;
+        }
+        graph.add_edge(prev, new);
+    }
+
+    if in_irq {
+        graph.usage[new] |= USED_IN_IRQ;
+    }
+    if irqs_enabled {
+        for &prev in held.classes[..held.len].iter() {
+            graph.usage[prev] |= HELD_WITH_IRQS_ENABLED;
+        }
+    }
+    let unsafe_class = irq_unsafe(&graph, core::iter::once(new).chain(held.classes[..held.len].iter().copied()));
+    if let Some(class) = unsafe_class {
+        report_irq_unsafe(&graph, &held, class);
+        return;
+    }
+
+    if held.len == MAX_HELD_LOCKS {
+        if turn_off() {
+            error!("LOCKDEP: More than {} locks held, validator turned off", MAX_HELD_LOCKS);
+        }
+        return;
+    }
+    let len = held.len;
+    held.classes[len] = new;
+    held.len += 1;
+}
+
+/// Called right before interrupts are enabled on the current CPU, the locks
+/// it holds are then held with interrupts enabled.
+pub fn irqs_enabled() {
+    if !is_enabled() {
+        return;
+    }
+    let mut graph = GRAPH.lock();
+    let held = HELD.get().lock();
+    if held.len == 0 {
+        return;
+    }
+    for &class in held.classes[..held.len].iter() {
+        graph.usage[class] |= HELD_WITH_IRQS_ENABLED;
+    }
+    if let Some(class) = irq_unsafe(&graph, held.classes[..held.len].iter().copied()) {
+        report_irq_unsafe(&graph, &held, class);
+    }
+}
+
+/// Record the release of a lock of `class`, which may be out of order.
+pub fn release(class: LockClass) {
+    if !is_enabled() {
+        return;
+    }
+    let graph = GRAPH.lock();
+    let mut held = HELD.get().lock();
+    let len = held.len;
+    let position = held.classes[..len].iter().rposition(|&index| ptr_eq(graph.name(index), class));
+    if let Some(position) = position {
+        held.classes.copy_within(position + 1..len, position);
+        held.len -= 1;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+pub mod atomic_waker;
+pub mod channel;
+pub mod irq_spin_lock;
+/// Lock dependency validator. Every `IrqSpinLock` belongs to the lock class
+/// of the place it was created at, a report is printed once an acquisition
+/// closes a cycle or a lock taken by interrupt handlers is held with
+/// interrupts enabled.
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
+pub mod mutex;
+pub mod notify;
+pub mod oneshot;
+pub mod rcu;
+pub mod rwlock;
+pub mod semaphore;
+pub mod wait_queue;
\ No newline at end of file
@@ -0,0 +1,83 @@
+use crate::sys::sync::wait_queue::WaitQueue;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Wakes waiting tasks without carrying data.
+///
+/// `notify_one` stores a single permit if no task is waiting, so the next
+/// `notified` completes at once. `notify_waiters` wakes only the tasks which
+/// called `notified` before it.
+pub struct Notify {
+    permit: AtomicBool,
+    /// Bumped by every `notify_waiters`.
+    generation: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+impl Notify {
+    pub const fn new() -> Self {
+        Self {
+            permit: AtomicBool::new(false),
+            generation: AtomicUsize::new(0),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Wait for a notification.
+    pub fn notified(&self) -> impl Future<Output=()> + '_ {
+        let generation = self.generation.load(Ordering::Acquire);
+        self.waiters.wait_until(move || {
+            let notified = self.generation.load(Ordering::Acquire) != generation
+                || self.permit.swap(false, Ordering::AcqRel);
+            notified.then_some(())
+        })
+    }
+
+    /// Wake one waiting task, or let the next `notified` complete at once.
+    /// Safe to call from interrupt context.
+    pub fn notify_one(&self) {
+        self.permit.store(true, Ordering::Release);
+        self.waiters.wake_one();
+    }
+
+    /// Wake every task currently waiting.
+    pub fn notify_waiters(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.waiters.wake_all();
+    }
+}
+
+/// A manual-reset event: once set, every wait completes until it is reset.
+pub struct Event {
+    set: AtomicBool,
+    waiters: WaitQueue,
+}
+
+impl Event {
+    pub const fn new() -> Self {
+        Self {
+            set: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Set the event and wake all waiters, safe to call from interrupt
+    /// context.
+    pub fn set(&self) {
+        self.set.store(true, Ordering::Release);
+        self.waiters.wake_all();
+    }
+
+    pub fn reset(&self) {
+        self.set.store(false, Ordering::Release);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.set.load(Ordering::Acquire)
+    }
+
+    /// Wait until the event is set.
+    pub fn wait(&self) -> impl Future<Output=()> + '_ {
+        self.waiters.wait_until(|| self.is_set().then_some(()))
+    }
+}
\ No newline at end of file
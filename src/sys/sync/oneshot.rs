@@ -0,0 +1,97 @@
+use crate::sys::sync::atomic_waker::AtomicWaker;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+
+/// The sender was dropped without sending a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+struct Inner<T> {
+    value: IrqSpinLock<Option<T>>,
+    /// Set once a value was sent or the sender dropped.
+    complete: AtomicBool,
+    receiver_dropped: AtomicBool,
+    waker: AtomicWaker,
+}
+
+/// Sends a single value, safe to use from interrupt context.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Future resolving to the value sent through the channel.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Create a channel carrying a single value.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        value: IrqSpinLock::new(None),
+        complete: AtomicBool::new(false),
+        receiver_dropped: AtomicBool::new(false),
+        waker: AtomicWaker::new(),
+    });
+    (Sender { inner: inner.clone() }, Receiver { inner })
+}
+
+impl<T> Sender<T> {
+    /// Send `value`, handing it back if the receiver is gone.
+    pub fn send(self, value: T) -> Result<(), T> {
+        if self.inner.receiver_dropped.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        *self.inner.value.lock() = Some(value);
+        self.inner.complete.store(true, Ordering::Release);
+        self.inner.waker.wake();
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.receiver_dropped.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if !self.inner.complete.swap(true, Ordering::AcqRel) {
+            self.inner.waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Take the value if it was already sent.
+    pub fn try_recv(&mut self) -> Option<Result<T, RecvError>> {
+        if !self.inner.complete.load(Ordering::Acquire) {
+            return None;
+        }
+        Some(self.inner.value.lock().take().ok_or(RecvError))
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(result) = self.try_recv() {
+            return Poll::Ready(result);
+        }
+        self.inner.waker.register(cx.waker());
+        // Catch a value sent before the waker was registered
+        match self.try_recv() {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.inner.receiver_dropped.store(true, Ordering::Release);
+    }
+}
\ No newline at end of file
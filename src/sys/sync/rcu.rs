@@ -0,0 +1,242 @@
+use crate::sys;
+use crate::sys::interrupt::softirq::{self, SoftIrq};
+use crate::sys::multitask::thread::{self, scheduler};
+use crate::sys::smp::{percpu, CpuMask};
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::sync::oneshot;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+struct RcuState {
+    /// Grace periods started so far.
+    gp_seq: u64,
+    /// Grace periods completed so far, a grace period is in progress while
+    /// it lags behind `gp_seq`.
+    completed: u64,
+    /// Callbacks with the grace period they wait for, in queueing order.
+    callbacks: VecDeque<(u64, Callback)>,
+}
+
+static STATE: IrqSpinLock<RcuState> = IrqSpinLock::new(RcuState {
+    gp_seq: 0,
+    completed: 0,
+    callbacks: VecDeque::new(),
+});
+/// CPUs which still have to pass a quiescent state in the current grace
+/// period.
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+crate::per_cpu! {
+    /// Depth of nested read sections.
+    static NESTING: AtomicUsize = AtomicUsize::new(0);
+    /// Set while the CPU is halted in its idle loop, it holds no reader then.
+    /// Cleared for the duration of an interrupt taken meanwhile.
+    static IDLE: AtomicBool = AtomicBool::new(false);
+    /// Set while an interrupt taken by an idle CPU runs, see [`irq_enter`].
+    static IDLE_IRQ: AtomicBool = AtomicBool::new(false);
+}
+
+pub fn init() {
+    softirq::open_softirq(SoftIrq::Rcu, run_callbacks);
+}
+
+/// Marks a read section, see [`rcu_read_lock`].
+///
+/// The guard is neither `Send` nor may it be held across an `await`, a task
+/// holding it could not be spawned.
+pub struct RcuReadGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Enter a read section. Data reached through RCU protected pointers stays
+/// alive until the guard is dropped. Read sections are not preemptible and
+/// must not block.
+pub fn rcu_read_lock() -> RcuReadGuard {
+    scheduler::preempt_disable();
+    NESTING.get().fetch_add(1, Ordering::Relaxed);
+    RcuReadGuard { _not_send: PhantomData }
+}
+
+impl Drop for RcuReadGuard {
+    fn drop(&mut self) {
+        NESTING.get().fetch_sub(1, Ordering::Relaxed);
+        scheduler::preempt_enable();
+    }
+}
+
+/// Report that the current CPU holds no reader, called on context switches
+/// and between executor tasks.
+pub fn note_quiescent_state() {
+    let cpu = sys::smp::current_id();
+    if PENDING.load(Ordering::Acquire) & (1 << cpu) == 0 {
+        return;
+    }
+    let mut state = STATE.lock();
+    let pending = PENDING.fetch_and(!(1 << cpu), Ordering::AcqRel) & !(1 << cpu);
+    if pending == 0 && state.completed != state.gp_seq {
+        complete_grace_period(&mut state);
+    }
+}
+
+/// Called from the tick, the interrupted context is quiescent unless it is
+/// inside a read section.
+pub fn tick() {
+    if NESTING.get().load(Ordering::Relaxed) == 0 {
+        note_quiescent_state();
+    }
+}
+
+/// The current CPU goes idle, grace periods no longer wait for it.
+pub fn enter_idle() {
+    IDLE.get().store(true, Ordering::SeqCst);
+    note_quiescent_state();
+}
+
+pub fn exit_idle() {
+    IDLE.get().store(false, Ordering::SeqCst);
+}
+
+/// Called on entry of a hardware interrupt. The handler, the softirqs run on
+/// its exit and a thread switched to from them may read, so an idle CPU takes
+/// part in grace periods again.
+pub fn irq_enter() {
+    if IDLE.get().swap(false, Ordering::SeqCst) {
+        IDLE_IRQ.get().store(true, Ordering::Relaxed);
+    }
+}
+
+/// Called on exit of a hardware interrupt after softirqs ran, the CPU goes
+/// back to idle if the interrupt found it idle.
+pub fn irq_exit() {
+    // An interrupt nested in softirqs returns to them, not to idle
+    if !softirq::in_softirq() && IDLE_IRQ.get().swap(false, Ordering::Relaxed) {
+        enter_idle();
+    }
+}
+
+/// Start a grace period if callbacks wait for one and none is in progress.
+fn start_grace_period(state: &mut RcuState) {
+    if state.completed != state.gp_seq || !state.callbacks.iter().any(|(seq, _)| *seq > state.completed) {
+        return;
+    }
+    state.gp_seq += 1;
+    // Pairs with the idle flag, a CPU leaving idle after this point only sees
+    // the updated pointers
+    fence(Ordering::SeqCst);
+    // An idle CPU entering an interrupt handler before `irq_enter` may be
+    // reading, it reports the quiescent state once it goes idle again
+    let mut pending = CpuMask::empty();
+    for cpu in sys::smp::online_mask().iter() {
+        if !IDLE.of(cpu).load(Ordering::SeqCst) || percpu::of(cpu).irq_depth() != 0 {
+            pending.set(cpu);
+        }
+    }
+    PENDING.store(pending.bits(), Ordering::Release);
+    if pending.is_empty() {
+        complete_grace_period(state);
+    }
+}
+
+fn complete_grace_period(state: &mut RcuState) {
+    state.completed = state.gp_seq;
+    softirq::raise_softirq(SoftIrq::Rcu);
+    start_grace_period(state);
+}
+
+/// Run the callbacks whose grace period has completed.
+fn run_callbacks() {
+    let ready: Vec<Callback> = {
+        let mut state = STATE.lock();
+        let count = state.callbacks.iter().take_while(|(seq, _)| *seq <= state.completed).count();
+        state.callbacks.drain(..count).map(|(_, callback)| callback).collect()
+    };
+    for callback in ready {
+        callback();
+    }
+}
+
+/// Run `callback` once every read section entered before this call has
+/// ended. Safe to call from interrupt context.
+pub fn call_rcu(callback: impl FnOnce() + Send + 'static) {
+    let mut state = STATE.lock();
+    // A grace period in progress may have started before the caller
+    // unpublished its data, so the next one is needed
+    let seq = state.gp_seq + 1;
+    state.callbacks.push_back((seq, Box::new(callback)));
+    start_grace_period(&mut state);
+}
+
+/// Block the current thread until every read section entered before this
+/// call has ended. Executor tasks use [`synchronize_rcu_async`] instead.
+pub fn synchronize_rcu() {
+    let current = thread::current();
+    let done = Arc::new(AtomicBool::new(false));
+    let callback_done = done.clone();
+    call_rcu(move || {
+        callback_done.store(true, Ordering::Release);
+        current.unpark();
+    });
+    while !done.load(Ordering::Acquire) {
+        thread::park();
+    }
+}
+
+/// Wait until every read section entered before this call has ended.
+pub async fn synchronize_rcu_async() {
+    let (sender, receiver) = oneshot::channel();
+    call_rcu(move || {
+        let _ = sender.send(());
+    });
+    let _ = receiver.await;
+}
+
+/// A pointer to a `T` which readers dereference inside read sections while
+/// writers replace it, the replaced value is dropped after a grace period.
+pub struct RcuCell<T> {
+    ptr: AtomicPtr<T>,
+}
+
+unsafe impl<T: Send + Sync> Send for RcuCell<T> {}
+unsafe impl<T: Send + Sync> Sync for RcuCell<T> {}
+
+/// A replaced value waiting for its grace period.
+struct Retired<T>(*mut T);
+
+unsafe impl<T: Send> Send for Retired<T> {}
+
+impl<T> Drop for Retired<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.0) });
+    }
+}
+
+impl<T: Send + Sync + 'static> RcuCell<T> {
+    pub fn new(value: T) -> Self {
+        Self { ptr: AtomicPtr::new(Box::into_raw(Box::new(value))) }
+    }
+
+    /// The current value, valid for the read section of `guard`.
+    pub fn read<'a>(&'a self, _guard: &'a RcuReadGuard) -> &'a T {
+        unsafe { &*self.ptr.load(Ordering::Acquire) }
+    }
+
+    /// Publish `value`, readers still holding the old one keep it until
+    /// their read sections end.
+    pub fn replace(&self, value: T) {
+        let old = self.ptr.swap(Box::into_raw(Box::new(value)), Ordering::AcqRel);
+        let retired = Retired(old);
+        call_rcu(move || drop(retired));
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(*self.ptr.get_mut()) });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::collections::VecDeque;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+struct Waiters {
+    next_key: u64,
+    list: VecDeque<(u64, Waker)>,
+}
+
+/// A FIFO queue of tasks waiting for a condition, the building block of the
+/// async primitives in this module.
+///
+/// Waiting tasks check their condition with the queue locked before they
+/// register, so a condition made true followed by a wake is never missed.
+/// Waking is safe from interrupt context.
+pub struct WaitQueue {
+    waiters: IrqSpinLock<Waiters>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            waiters: IrqSpinLock::new(Waiters { next_key: 0, list: VecDeque::new() }),
+        }
+    }
+
+    /// Wait until `condition` returns `Some`, resolving to its value.
+    ///
+    /// `condition` is called on every poll and may have side effects like
+    /// taking a lock, it runs with the queue locked and must not wake it.
+    pub fn wait_until<R, F>(&self, condition: F) -> WaitUntil<'_, F>
+    where
+        F: FnMut() -> Option<R> + Unpin,
+    {
+        WaitUntil { queue: self, condition, key: None, done: false }
+    }
+
+    /// Wake the longest waiting task, returns `false` if none was waiting.
+    pub fn wake_one(&self) -> bool {
+        let waiter = self.waiters.lock().list.pop_front();
+        match waiter {
+            Some((_, waker)) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wake every waiting task, returns how many were woken.
+    pub fn wake_all(&self) -> usize {
+        let list = mem::take(&mut self.waiters.lock().list);
+        let count = list.len();
+        for (_, waker) in list {
+            waker.wake();
+        }
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiters.lock().list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub struct WaitUntil<'a, F> {
+    queue: &'a WaitQueue,
+    condition: F,
+    /// Key of the registered waker, stays set after a wake removed it.
+    key: Option<u64>,
+    done: bool,
+}
+
+impl<R, F> Future for WaitUntil<'_, F>
+where
+    F: FnMut() -> Option<R> + Unpin,
+{
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let this = &mut *self;
+        let mut waiters = this.queue.waiters.lock();
+        if let Some(result) = (this.condition)() {
+            if let Some(key) = this.key {
+                waiters.list.retain(|(k, _)| *k != key);
+            }
+            this.done = true;
+            return Poll::Ready(result);
+        }
+
+        let registered = this.key.and_then(|key| waiters.list.iter_mut().find(|(k, _)| *k == key));
+        match registered {
+            Some((_, waker)) => {
+                if !waker.will_wake(cx.waker()) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            None => {
+                // First poll, or woken without the condition holding anymore
+                let key = waiters.next_key;
+                waiters.next_key += 1;
+                waiters.list.push_back((key, cx.waker().clone()));
+                this.key = Some(key);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<F> Drop for WaitUntil<'_, F> {
+    fn drop(&mut self) {
+        let Some(key) = self.key.filter(|_| !self.done) else { return };
+        let woken = {
+            let mut waiters = self.queue.waiters.lock();
+            let before = waiters.list.len();
+            waiters.list.retain(|(k, _)| *k != key);
+            waiters.list.len() == before
+        };
+        // Dropped after a `wake_one` picked it, hand the wakeup on
+        if woken {
+            self.queue.wake_one();
+        }
+    }
+}
\ No newline at end of file
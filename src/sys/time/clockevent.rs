@@ -0,0 +1,32 @@
+use crate::abstracts::time::clockevent::ClockEventDevice;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use alloc::sync::Arc;
+use conquer_once::spin::OnceCell;
+
+/// Callback invoked on every event of the clock event device.
+pub type ClockEventHandler = fn();
+
+static DEVICE: OnceCell<Arc<dyn ClockEventDevice>> = OnceCell::uninit();
+/// Read by the timer interrupt, so written with it masked.
+static HANDLER: IrqSpinLock<Option<ClockEventHandler>> = IrqSpinLock::new(None);
+
+/// Register the clock event device of the system.
+pub fn register_device(device: Arc<dyn ClockEventDevice>) {
+    DEVICE.init_once(|| device);
+}
+
+pub fn device() -> &'static Arc<dyn ClockEventDevice> {
+    DEVICE.get().expect("Clock event device not registered")
+}
+
+pub fn set_event_handler(handler: ClockEventHandler) {
+    *HANDLER.lock() = Some(handler);
+}
+
+/// Entry of the clock event interrupt.
+pub fn handle_event() {
+    let handler = *HANDLER.lock();
+    if let Some(handler) = handler {
+        handler();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,143 @@
+use crate::abstracts::time::clocksource::ClockSource;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::time::NSEC_PER_SEC;
+use core::sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering};
+use log::{error, info};
+use spin::Once;
+
+/// Maximum number of registered clock sources.
+const MAX_CLOCKSOURCES: usize = 8;
+const NO_SOURCE: usize = usize::MAX;
+
+static SOURCES: [Once<&'static dyn ClockSource>; MAX_CLOCKSOURCES] = [const { Once::new() }; MAX_CLOCKSOURCES];
+
+/// Timekeeping state, published with a sequence counter so readers on any
+/// CPU, including interrupt handlers, never block.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+/// Index of the current clock source in `SOURCES`.
+static CURRENT: AtomicUsize = AtomicUsize::new(NO_SOURCE);
+/// Counter value at the last update.
+static CYCLE_LAST: AtomicU64 = AtomicU64::new(0);
+/// Monotonic time at the last update, in nanoseconds.
+static BASE_NS: AtomicU64 = AtomicU64::new(0);
+/// Conversion factor of the current clock source, `ns = cycles * mult >> 32`.
+static MULT: AtomicU64 = AtomicU64::new(0);
+/// Serializes writers of the timekeeping state.
+static WRITER: IrqSpinLock<()> = IrqSpinLock::new(());
+
+/// Latest time handed out, keeps the clock monotonic even if the counters of
+/// two CPUs are slightly apart.
+static LAST_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Register a clock source, and switch to it if its rating is the best.
+pub fn register(source: &'static dyn ClockSource) {
+    if source.frequency() == 0 {
+        error!("Clock source {} has no frequency", source.name());
+        return;
+    }
+
+    let _guard = WRITER.lock();
+    let Some((index, slot)) = SOURCES.iter().enumerate().find(|(_, slot)| slot.get().is_none()) else {
+        error!("Too many clock sources, {} ignored", source.name());
+        return;
+    };
+    slot.call_once(|| source);
+    info!("Clock source registered: {} ({} Hz, rating {})", source.name(), source.frequency(), source.rating());
+
+    if current().map_or(true, |current| source.rating() > current.rating()) {
+        switch_to(index);
+    }
+}
+
+/// Registered clock sources.
+pub fn sources() -> impl Iterator<Item=&'static dyn ClockSource> {
+    SOURCES.iter().map_while(|slot| slot.get().copied())
+}
+
+/// The clock source backing the monotonic clock.
+pub fn current() -> Option<&'static dyn ClockSource> {
+    match CURRENT.load(Ordering::Acquire) {
+        NO_SOURCE => None,
+        index => SOURCES[index].get().copied(),
+    }
+}
+
+/// Monotonic time since the first clock source was registered, in
+/// nanoseconds.
+pub fn monotonic_ns() -> u64 {
+    loop {
+        if let Some(ns) = try_monotonic_ns() {
+            return ns;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Like [`monotonic_ns`], but gives up with `None` instead of waiting while
+/// an update is in progress. For the logger, which may run on the panic path
+/// after the updating CPU was stopped.
+pub fn try_monotonic_ns() -> Option<u64> {
+    let sequence = SEQUENCE.load(Ordering::Acquire);
+    if sequence & 1 != 0 {
+        return None;
+    }
+
+    let Some(source) = current() else { return Some(0) };
+    let cycle_last = CYCLE_LAST.load(Ordering::Relaxed);
+    let base_ns = BASE_NS.load(Ordering::Relaxed);
+    let mult = MULT.load(Ordering::Relaxed);
+    let now = source.read();
+
+    fence(Ordering::Acquire);
+    if SEQUENCE.load(Ordering::Relaxed) != sequence {
+        return None;
+    }
+    let ns = base_ns + cycles_to_ns(now.wrapping_sub(cycle_last) & source.mask(), mult);
+    Some(LAST_NS.fetch_max(ns, Ordering::Relaxed).max(ns))
+}
+
+/// Longest time the monotonic clock may go without `update`, half the
+/// wrap-around period of the current counter.
+pub fn max_idle_ns() -> u64 {
+    match current() {
+        Some(source) => cycles_to_ns(source.mask() / 2, MULT.load(Ordering::Relaxed)),
+        None => 0,
+    }
+}
+
+/// Fold the elapsed cycles into the base time, must run more often than the
+/// current counter wraps around. Called on every tick of the BSP.
+pub fn update() {
+    let _guard = WRITER.lock();
+    if let Some(source) = current() {
+        write_state(source, CURRENT.load(Ordering::Relaxed));
+    }
+}
+
+/// Make the source at `index` current, must be called with `WRITER` held.
+fn switch_to(index: usize) {
+    let source = *SOURCES[index].get().unwrap();
+    write_state(source, index);
+}
+
+fn write_state(source: &'static dyn ClockSource, index: usize) {
+    let base_ns = match current() {
+        Some(current) => {
+            let elapsed = current.read().wrapping_sub(CYCLE_LAST.load(Ordering::Relaxed)) & current.mask();
+            BASE_NS.load(Ordering::Relaxed) + cycles_to_ns(elapsed, MULT.load(Ordering::Relaxed))
+        }
+        None => 0,
+    };
+
+    SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    fence(Ordering::Release);
+    CYCLE_LAST.store(source.read(), Ordering::Relaxed);
+    BASE_NS.store(base_ns, Ordering::Relaxed);
+    MULT.store(((NSEC_PER_SEC as u128) << 32).div_ceil(source.frequency() as u128) as u64, Ordering::Relaxed);
+    CURRENT.store(index, Ordering::Relaxed);
+    SEQUENCE.fetch_add(1, Ordering::Release);
+}
+
+fn cycles_to_ns(cycles: u64, mult: u64) -> u64 {
+    ((cycles as u128 * mult as u128) >> 32) as u64
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+use log::info;
+
+pub mod clockevent;
+pub mod clocksource;
+pub mod nohz;
+pub mod tick;
+pub mod timer;
+pub mod wall;
+
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+pub fn module_init() {
+    info!("☞ Hikari Time Module");
+    let device = clockevent::device();
+    info!("Clock event device: {} ({:?})", device.name(), device.features());
+    if let Some(source) = clocksource::current() {
+        info!("Clock source: {} ({} Hz)", source.name(), source.frequency());
+    }
+    timer::init();
+    tick::start();
+}
\ No newline at end of file
@@ -0,0 +1,148 @@
+use crate::abstracts::time::clockevent::ClockEventFeatures;
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::sync::rcu;
+use crate::sys::time::{clockevent, clocksource, tick, timer};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use log::{error, info};
+
+/// Idle state of a CPU.
+struct CpuIdle {
+    /// Set from `idle_enter` to `idle_exit`.
+    idle: AtomicBool,
+    /// Set while the CPU sleeps with its periodic tick stopped.
+    tick_stopped: AtomicBool,
+    /// Monotonic time the current idle period started at.
+    entered_ns: AtomicU64,
+    entries: AtomicU64,
+    tickless_entries: AtomicU64,
+    residency_ns: AtomicU64,
+}
+
+impl CpuIdle {
+    const fn new() -> Self {
+        Self {
+            idle: AtomicBool::new(false),
+            tick_stopped: AtomicBool::new(false),
+            entered_ns: AtomicU64::new(0),
+            entries: AtomicU64::new(0),
+            tickless_entries: AtomicU64::new(0),
+            residency_ns: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Idle statistics of a CPU.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleStats {
+    /// Number of idle periods.
+    pub entries: u64,
+    /// Number of idle periods spent with the tick stopped.
+    pub tickless_entries: u64,
+    /// Total time spent idle, in nanoseconds.
+    pub residency_ns: u64,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static CPU_IDLE: [CpuIdle; KERNEL_MAX_CPU_NUM] = [const { CpuIdle::new() }; KERNEL_MAX_CPU_NUM];
+
+/// Let idle CPUs stop their periodic tick.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Keep the periodic tick running on idle CPUs.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Whether `cpu` is between `idle_enter` and `idle_exit`. Interrupts taken
+/// meanwhile must not switch threads, the tick may still be stopped.
+pub fn is_idle(cpu: usize) -> bool {
+    CPU_IDLE[cpu].idle.load(Ordering::Acquire)
+}
+
+/// Whether `cpu` is idle with its tick stopped.
+pub fn is_tick_stopped(cpu: usize) -> bool {
+    CPU_IDLE[cpu].tick_stopped.load(Ordering::Acquire)
+}
+
+/// Enter an idle period on the current CPU, called with interrupts disabled
+/// right before halting.
+///
+/// If nothing is due within the next tick, the clock event device is switched
+/// to one-shot mode and armed for the next timer deadline only. The wait is
+/// bounded by the device range and by the wrap-around of the clock source.
+pub fn idle_enter() {
+    let cpu = sys::smp::current_id();
+    let idle = &CPU_IDLE[cpu];
+    let now = clocksource::monotonic_ns();
+    idle.entered_ns.store(now, Ordering::Relaxed);
+    idle.entries.fetch_add(1, Ordering::Relaxed);
+    idle.idle.store(true, Ordering::Release);
+    rcu::enter_idle();
+
+    let device = clockevent::device();
+    if !is_enabled() || !device.features().contains(ClockEventFeatures::ONESHOT) {
+        return;
+    }
+    let delta = timer::next_deadline(cpu)
+        .saturating_sub(now)
+        .min(device.max_delta_ns())
+        .min(clocksource::max_idle_ns());
+    if delta <= tick::TICK_NSEC {
+        return;
+    }
+    if let Err(err) = device.set_oneshot().and_then(|_| device.program_event(delta)) {
+        error!("Failed to stop the tick on CPU {}: {:?}", cpu, err);
+        tick::resume();
+        return;
+    }
+    idle.tick_stopped.store(true, Ordering::Release);
+    idle.tickless_entries.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Leave the idle period of the current CPU, restarting its periodic tick if
+/// it was stopped.
+pub fn idle_exit() {
+    let cpu = sys::smp::current_id();
+    let idle = &CPU_IDLE[cpu];
+    rcu::exit_idle();
+    let slept_ns = clocksource::monotonic_ns().saturating_sub(idle.entered_ns.load(Ordering::Relaxed));
+    idle.residency_ns.fetch_add(slept_ns, Ordering::Relaxed);
+
+    if idle.tick_stopped.swap(false, Ordering::AcqRel) {
+        tick::catch_up(cpu, slept_ns);
+        tick::resume();
+    }
+    idle.idle.store(false, Ordering::Release);
+}
+
+pub fn idle_stats(cpu: usize) -> IdleStats {
+    let idle = &CPU_IDLE[cpu];
+    IdleStats {
+        entries: idle.entries.load(Ordering::Relaxed),
+        tickless_entries: idle.tickless_entries.load(Ordering::Relaxed),
+        residency_ns: idle.residency_ns.load(Ordering::Relaxed),
+    }
+}
+
+/// Print the idle residency of every online CPU.
+pub fn dump_stats() {
+    let uptime_ns = clocksource::monotonic_ns().max(1);
+    for cpu in 0..sys::smp::cpu_count() {
+        let stats = idle_stats(cpu);
+        info!(
+            "CPU {}: idle {} ms ({}%), {} entries, {} tickless",
+            cpu,
+            stats.residency_ns / 1_000_000,
+            stats.residency_ns * 100 / uptime_ns,
+            stats.entries,
+            stats.tickless_entries,
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,59 @@
+use crate::kinfo::{KERNEL_MAX_CPU_NUM, KERNEL_TICK_HZ};
+use crate::sys;
+use crate::sys::time::{clockevent, clocksource, nohz, timer, NSEC_PER_SEC};
+use core::sync::atomic::{AtomicU64, Ordering};
+use log::error;
+
+/// Length of a tick in nanoseconds.
+pub const TICK_NSEC: u64 = NSEC_PER_SEC / KERNEL_TICK_HZ;
+
+/// Number of ticks since boot, advanced by the BSP.
+static JIFFIES: AtomicU64 = AtomicU64::new(0);
+/// Number of ticks handled by each CPU.
+static CPU_TICKS: [AtomicU64; KERNEL_MAX_CPU_NUM] = [const { AtomicU64::new(0) }; KERNEL_MAX_CPU_NUM];
+
+/// Start the periodic tick on the current CPU.
+pub fn start() {
+    clockevent::set_event_handler(handle_tick);
+    clockevent::device()
+        .set_periodic(TICK_NSEC)
+        .expect("Failed to start the periodic tick");
+}
+
+/// Restart the periodic tick of the current CPU after an idle period without
+/// it.
+pub fn resume() {
+    if let Err(err) = clockevent::device().set_periodic(TICK_NSEC) {
+        error!("Failed to resume the periodic tick: {:?}", err);
+    }
+}
+
+/// Account the ticks `cpu` skipped while idle for `idle_ns` nanoseconds.
+pub fn catch_up(cpu: usize, idle_ns: u64) {
+    if cpu == 0 {
+        JIFFIES.fetch_add(idle_ns / TICK_NSEC, Ordering::Relaxed);
+        clocksource::update();
+    }
+}
+
+fn handle_tick() {
+    let cpu = sys::smp::current_id();
+    CPU_TICKS[cpu].fetch_add(1, Ordering::Relaxed);
+    // Jiffies skipped by a stopped tick are accounted by `catch_up`
+    if cpu == 0 && !nohz::is_tick_stopped(cpu) {
+        let jiffies = JIFFIES.fetch_add(1, Ordering::Relaxed) + 1;
+        clocksource::update();
+        sys::interrupt::balance::tick(jiffies);
+    }
+    timer::check_expired();
+    sys::sync::rcu::tick();
+    sys::multitask::thread::scheduler::tick();
+}
+
+pub fn jiffies() -> u64 {
+    JIFFIES.load(Ordering::Relaxed)
+}
+
+pub fn cpu_ticks(cpu: usize) -> u64 {
+    CPU_TICKS[cpu].load(Ordering::Relaxed)
+}
\ No newline at end of file
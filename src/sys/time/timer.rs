@@ -0,0 +1,243 @@
+use crate::kinfo::KERNEL_MAX_CPU_NUM;
+use crate::sys;
+use crate::sys::interrupt::softirq::{self, SoftIrq};
+use crate::sys::sync::atomic_waker::AtomicWaker;
+use crate::sys::sync::irq_spin_lock::IrqSpinLock;
+use crate::sys::time::clocksource;
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering as CmpOrdering;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+/// `NEXT_DEADLINES` value of a CPU without pending timers.
+pub const NO_DEADLINE: u64 = u64::MAX;
+
+enum TimerAction {
+    Wake,
+    Call(Box<dyn FnOnce() + Send>),
+}
+
+/// `TimerState::status` of a timer which neither fired nor was cancelled.
+const TIMER_PENDING: u8 = 0;
+const TIMER_FIRED: u8 = 1;
+const TIMER_CANCELLED: u8 = 2;
+
+/// State shared between a pending timer and its owner.
+struct TimerState {
+    /// Leaves `TIMER_PENDING` exactly once, so either the timer fires or its
+    /// cancellation succeeds.
+    status: AtomicU8,
+    waker: AtomicWaker,
+}
+
+impl TimerState {
+    fn transition(&self, to: u8) -> bool {
+        self.status.compare_exchange(TIMER_PENDING, to, Ordering::AcqRel, Ordering::Acquire).is_ok()
+    }
+
+    fn is_fired(&self) -> bool {
+        self.status.load(Ordering::Acquire) == TIMER_FIRED
+    }
+}
+
+struct TimerEntry {
+    deadline: u64,
+    /// Tie breaker keeping timers with the same deadline in insertion order.
+    seq: u64,
+    state: Arc<TimerState>,
+    action: TimerAction,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        (self.deadline, self.seq) == (other.deadline, other.seq)
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    /// Reversed, so the max-heap pops the earliest deadline first.
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (other.deadline, other.seq).cmp(&(self.deadline, self.seq))
+    }
+}
+
+static TIMER_HEAPS: [IrqSpinLock<BinaryHeap<TimerEntry>>; KERNEL_MAX_CPU_NUM] =
+    [const { IrqSpinLock::new(BinaryHeap::new()) }; KERNEL_MAX_CPU_NUM];
+/// Earliest deadline on each CPU, readable without the heap lock.
+static NEXT_DEADLINES: [AtomicU64; KERNEL_MAX_CPU_NUM] = [const { AtomicU64::new(NO_DEADLINE) }; KERNEL_MAX_CPU_NUM];
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+fn add(deadline: u64, action: TimerAction) -> Arc<TimerState> {
+    let state = Arc::new(TimerState {
+        status: AtomicU8::new(TIMER_PENDING),
+        waker: AtomicWaker::new(),
+    });
+    let cpu = sys::smp::current_id();
+    let mut heap = TIMER_HEAPS[cpu].lock();
+    heap.push(TimerEntry {
+        deadline,
+        seq: NEXT_SEQ.fetch_add(1, Ordering::Relaxed),
+        state: state.clone(),
+        action,
+    });
+    NEXT_DEADLINES[cpu].fetch_min(deadline, Ordering::Release);
+    state
+}
+
+/// Earliest timer deadline of `cpu` on the monotonic clock, `NO_DEADLINE` if
+/// none is pending.
+pub fn next_deadline(cpu: usize) -> u64 {
+    NEXT_DEADLINES[cpu].load(Ordering::Acquire)
+}
+
+/// Raise the timer softirq if a timer of the current CPU expired. Called from
+/// the clock event interrupt.
+pub fn check_expired() {
+    if next_deadline(sys::smp::current_id()) <= clocksource::monotonic_ns() {
+        softirq::raise_softirq(SoftIrq::Timer);
+    }
+}
+
+/// Handler of the timer softirq, fires every expired timer of the current CPU.
+fn run_timers() {
+    let cpu = sys::smp::current_id();
+    let now = clocksource::monotonic_ns();
+    let mut expired = Vec::new();
+    {
+        let mut heap = TIMER_HEAPS[cpu].lock();
+        while heap.peek().map_or(false, |entry| entry.deadline <= now) {
+            expired.push(heap.pop().unwrap());
+        }
+        let next = heap.peek().map_or(NO_DEADLINE, |entry| entry.deadline);
+        NEXT_DEADLINES[cpu].store(next, Ordering::Release);
+    }
+
+    for entry in expired {
+        if !entry.state.transition(TIMER_FIRED) {
+            continue;
+        }
+        match entry.action {
+            TimerAction::Wake => entry.state.waker.wake(),
+            TimerAction::Call(callback) => callback(),
+        }
+    }
+}
+
+/// Handle of a callback timer.
+pub struct TimerHandle {
+    state: Arc<TimerState>,
+}
+
+impl TimerHandle {
+    /// Prevent the callback from running. Returns `false` if it already
+    /// started running, or the timer was cancelled before.
+    pub fn cancel(&self) -> bool {
+        self.state.transition(TIMER_CANCELLED)
+    }
+}
+
+/// Run `callback` in softirq context on the current CPU once the monotonic
+/// clock reaches `deadline_ns`.
+pub fn add_timer<F>(deadline_ns: u64, callback: F) -> TimerHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    TimerHandle { state: add(deadline_ns, TimerAction::Call(Box::new(callback))) }
+}
+
+/// Future completing once the monotonic clock reaches its deadline.
+pub struct Sleep {
+    deadline: u64,
+    state: Option<Arc<TimerState>>,
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> u64 {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if clocksource::monotonic_ns() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let deadline = self.deadline;
+        let state = self.state.get_or_insert_with(|| add(deadline, TimerAction::Wake));
+        state.waker.register(cx.waker());
+        // Catch a timer which fired before the waker was registered
+        if state.is_fired() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            state.transition(TIMER_CANCELLED);
+        }
+    }
+}
+
+/// Sleep until the monotonic clock reaches `deadline_ns`.
+pub fn sleep_until(deadline_ns: u64) -> Sleep {
+    Sleep { deadline: deadline_ns, state: None }
+}
+
+/// Sleep for `duration`.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(clocksource::monotonic_ns().saturating_add(duration.as_nanos() as u64))
+}
+
+/// Error of a [`Timeout`] whose deadline passed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+/// Future resolving to the output of the inner future, or [`Elapsed`] if the
+/// deadline passes first.
+pub struct Timeout<F> {
+    future: Pin<Box<F>>,
+    sleep: Sleep,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Run `future` for at most `duration`.
+pub fn timeout<F: Future>(future: F, duration: Duration) -> Timeout<F> {
+    Timeout { future: Box::pin(future), sleep: sleep(duration) }
+}
+
+pub fn init() {
+    softirq::open_softirq(SoftIrq::Timer, run_timers);
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use crate::sys::time::{clocksource, NSEC_PER_SEC};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Wall clock time of the monotonic clock origin, in nanoseconds since the
+/// Unix epoch.
+static BOOT_REALTIME_NS: AtomicU64 = AtomicU64::new(0);
+static REALTIME_SET: AtomicBool = AtomicBool::new(false);
+
+/// A calendar date and time in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Seconds since the Unix epoch, for dates from 1970 on.
+    pub fn to_unix_secs(&self) -> u64 {
+        // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+        days as u64 * SECS_PER_DAY + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+
+    pub fn from_unix_secs(secs: u64) -> Self {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = (secs / SECS_PER_DAY) as i64 + 719_468;
+        let secs_of_day = secs % SECS_PER_DAY;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as u16;
+        Self {
+            year,
+            month,
+            day,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Set the wall clock to `realtime_ns` nanoseconds since the Unix epoch.
+pub fn set_realtime_ns(realtime_ns: u64) {
+    BOOT_REALTIME_NS.store(realtime_ns - clocksource::monotonic_ns(), Ordering::Relaxed);
+    REALTIME_SET.store(true, Ordering::Release);
+}
+
+/// Nanoseconds since the Unix epoch, `None` until the wall clock is set.
+pub fn realtime_ns() -> Option<u64> {
+    if !REALTIME_SET.load(Ordering::Acquire) {
+        return None;
+    }
+    Some(BOOT_REALTIME_NS.load(Ordering::Relaxed) + clocksource::monotonic_ns())
+}
+
+/// Current date and time, `None` until the wall clock is set.
+pub fn now() -> Option<DateTime> {
+    realtime_ns().map(|ns| DateTime::from_unix_secs(ns / NSEC_PER_SEC))
+}
+
+/// Like [`now`], but also `None` if the clock can not be read without
+/// waiting, see [`clocksource::try_monotonic_ns`].
+pub fn try_now() -> Option<DateTime> {
+    if !REALTIME_SET.load(Ordering::Acquire) {
+        return None;
+    }
+    let ns = BOOT_REALTIME_NS.load(Ordering::Relaxed) + clocksource::try_monotonic_ns()?;
+    Some(DateTime::from_unix_secs(ns / NSEC_PER_SEC))
+}
\ No newline at end of file